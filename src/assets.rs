@@ -1,10 +1,22 @@
+use bevy::asset::{Asset, AssetLoader, LoadContext, LoadedAsset};
 use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+use crate::enemy;
+use crate::tower;
+use crate::wave;
 
 #[derive(Resource)]
 pub struct GameAssets {
     pub hexagon_mesh: Handle<Mesh>,
     pub tower_mesh: Handle<Mesh>,
     pub wall_mesh: Handle<Mesh>,
+    pub waves: Handle<wave::WaveDefinitions>,
+    pub enemies: Handle<enemy::EnemyArchetypes>,
+    pub towers: Handle<tower::TowerDefinitions>,
+    pub font: Handle<Font>,
 }
 
 pub fn asset_loading(mut commands: Commands, assets: Res<AssetServer>) {
@@ -12,5 +24,42 @@ pub fn asset_loading(mut commands: Commands, assets: Res<AssetServer>) {
         hexagon_mesh: assets.load("models/hexagon.glb#Mesh0/Primitive0"),
         tower_mesh: assets.load("models/tower.glb#Mesh0/Primitive0"),
         wall_mesh: assets.load("models/wall.glb#Mesh0/Primitive0"),
+        waves: assets.load("data/default.waves.ron"),
+        enemies: assets.load("data/default.enemies.ron"),
+        towers: assets.load("data/default.towers.ron"),
+        font: assets.load("fonts/FiraSans-Bold.ttf"),
     });
 }
+
+/// loads any deserializable asset from a RON file (extensions can be multi-part e.g. "waves.ron")
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::enemy;
+use crate::goal;
+use crate::status;
+use crate::targeting;
+use crate::tower;
+
+pub struct AuraPlugin;
+impl Plugin for AuraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Aura>()
+            .register_type::<Buffs>()
+            .add_system(
+                buff_towers
+                    .before(targeting::acquire_targets)
+                    .before(tower::tower_shoot),
+            )
+            .add_system(pulse_enemy_auras.run_if(in_state(goal::GameState::Playing)));
+    }
+}
+
+/// what a support tower does to everything within its range
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct AuraDefinition {
+    pub buffs: Buffs,
+    pub enemy_effects: Vec<status::StatusEffect>,
+    /// seconds between applying `enemy_effects` to the enemies in range
+    pub interval: f32,
+}
+
+impl Default for AuraDefinition {
+    fn default() -> Self {
+        Self {
+            buffs: Buffs::default(),
+            enemy_effects: vec![],
+            interval: 0.5,
+        }
+    }
+}
+
+/// stat multipliers, both given out by an aura and the combined bonus on a tower
+#[derive(Clone, Copy, Component, Debug, Deserialize, PartialEq, Reflect)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct Buffs {
+    pub fire_rate: f32,
+    pub range: f32,
+    pub damage: f32,
+}
+
+impl Default for Buffs {
+    fn default() -> Self {
+        Self {
+            fire_rate: 1.,
+            range: 1.,
+            damage: 1.,
+        }
+    }
+}
+
+impl Buffs {
+    /// auras don't add up: each stat takes the strongest bonus on offer
+    pub fn strongest(self, other: Buffs) -> Buffs {
+        Buffs {
+            fire_rate: self.fire_rate.max(other.fire_rate),
+            range: self.range.max(other.range),
+            damage: self.damage.max(other.damage),
+        }
+    }
+}
+
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Aura {
+    pub buffs: Buffs,
+    pub enemy_effects: Vec<status::StatusEffect>,
+    pub timer: Timer,
+}
+
+impl Aura {
+    pub fn new(definition: &AuraDefinition) -> Self {
+        Self {
+            buffs: definition.buffs,
+            enemy_effects: definition.enemy_effects.clone(),
+            timer: Timer::from_seconds(definition.interval, TimerMode::Repeating),
+        }
+    }
+}
+
+/// recomputes every tower's buffs from the auras in range (a tower doesn't buff itself)
+pub fn buff_towers(
+    auras: Query<(Entity, &Transform, &tower::Range, &Aura)>,
+    mut towers: Query<(Entity, &Transform, &mut Buffs), With<tower::Tower>>,
+) {
+    for (entity, transform, mut buffs) in towers.iter_mut() {
+        let combined = auras
+            .iter()
+            .filter(|(aura_entity, aura_transform, range, _)| {
+                *aura_entity != entity
+                    && (aura_transform.translation - transform.translation).length() < range.range
+            })
+            .fold(Buffs::default(), |combined, (_, _, _, aura)| {
+                combined.strongest(aura.buffs)
+            });
+        if *buffs != combined {
+            *buffs = combined;
+        }
+    }
+}
+
+/// applies aura effects to the enemies in range (the strongest aura of each kind wins)
+pub fn pulse_enemy_auras(
+    time: Res<Time>,
+    mut auras: Query<(Entity, &Transform, &tower::Range, &mut Aura)>,
+    mut enemies: Query<(&Transform, &mut status::StatusEffects), With<enemy::Enemy>>,
+) {
+    for (entity, transform, range, mut aura) in auras.iter_mut() {
+        if aura.enemy_effects.is_empty() {
+            continue;
+        }
+        aura.timer.tick(time.delta());
+        if !aura.timer.just_finished() {
+            continue;
+        }
+        for (enemy_transform, mut status) in enemies.iter_mut() {
+            if (enemy_transform.translation - transform.translation).length() >= range.range {
+                continue;
+            }
+            for effect in aura.enemy_effects.iter() {
+                status.apply_aura(effect, entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strongest_buff_of_each_kind_wins() {
+        let fast = Buffs {
+            fire_rate: 1.5,
+            ..Default::default()
+        };
+        let strong = Buffs {
+            fire_rate: 1.2,
+            damage: 1.3,
+            ..Default::default()
+        };
+        let combined = Buffs::default().strongest(fast).strongest(strong);
+        assert_eq!(
+            combined,
+            Buffs {
+                fire_rate: 1.5,
+                range: 1.,
+                damage: 1.3,
+            }
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::enemy;
+use crate::health;
+use crate::wave;
+
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SpeedBurst>()
+            .register_type::<RegenSpike>()
+            .add_system(advance_phases)
+            .add_system(tick_speed_bursts)
+            .add_system(tick_regen_spikes);
+    }
+}
+
+/// a phase starts once the boss drops to `threshold` (fraction of max hp)
+#[derive(Clone, Debug, Deserialize)]
+pub struct BossPhase {
+    pub threshold: f32,
+    pub behaviours: Vec<PhaseBehaviour>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum PhaseBehaviour {
+    SpeedBurst { multiplier: f32, seconds: f32 },
+    SpawnMinions { archetype: String, count: usize },
+    Shield { seconds: f32 },
+    RegenSpike {
+        amount: health::RegenAmount,
+        interval: f32,
+        seconds: f32,
+    },
+}
+
+/// phase state machine for a boss (phases are entered in order of decreasing threshold)
+#[derive(Clone, Component, Default)]
+pub struct Boss {
+    pub phases: Vec<BossPhase>,
+    /// number of phases entered so far
+    pub current: usize,
+}
+
+impl Boss {
+    pub fn new(mut phases: Vec<BossPhase>) -> Self {
+        phases.sort_by(|a, b| b.threshold.total_cmp(&a.threshold));
+        Self { phases, current: 0 }
+    }
+}
+
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct SpeedBurst {
+    pub multiplier: f32,
+    pub timer: Timer,
+}
+
+/// temporary regen that replaces the boss' normal regen until the timer runs out
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct RegenSpike {
+    pub timer: Timer,
+    pub previous: Option<health::Regen>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn advance_phases(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Boss,
+        &health::HealthPoints,
+        &Transform,
+        Option<&health::Regen>,
+        Option<&RegenSpike>,
+        Option<&wave::WaveMember>,
+    )>,
+    mut spawn_events: EventWriter<enemy::SpawnEnemy>,
+) {
+    let rng = &mut rand::thread_rng();
+    for (entity, mut boss, hp, transform, regen, spike, member) in query.iter_mut() {
+        // a spike that is already running keeps hold of the regen to restore
+        let previous = match spike {
+            Some(spike) => spike.previous.clone(),
+            None => regen.cloned(),
+        };
+        let fraction = hp.hp as f32 / hp.max_hp as f32;
+        while let Some(phase) = boss.phases.get(boss.current).cloned() {
+            if fraction > phase.threshold {
+                break;
+            }
+            boss.current += 1;
+            info!("{:?} entered boss phase {}", entity, boss.current);
+            for behaviour in phase.behaviours {
+                match behaviour {
+                    PhaseBehaviour::SpeedBurst {
+                        multiplier,
+                        seconds,
+                    } => {
+                        commands.entity(entity).insert(SpeedBurst {
+                            multiplier,
+                            timer: Timer::from_seconds(seconds, TimerMode::Once),
+                        });
+                    }
+                    PhaseBehaviour::SpawnMinions { archetype, count } => {
+                        for _ in 0..count {
+                            let (jitter_x, jitter_z) =
+                                (rng.gen_range(-1.5..1.5), rng.gen_range(-1.5..1.5));
+                            spawn_events.send(enemy::SpawnEnemy {
+                                archetype: archetype.clone(),
+                                position: Vec3::new(
+                                    transform.translation.x + jitter_x,
+                                    0.,
+                                    transform.translation.z + jitter_z,
+                                ),
+                                // minions hold up the boss' wave too
+                                wave: member.map(|member| member.wave),
+                            });
+                        }
+                    }
+                    PhaseBehaviour::Shield { seconds } => {
+                        commands.entity(entity).insert(health::Invulnerable {
+                            timer: Timer::from_seconds(seconds, TimerMode::Once),
+                        });
+                    }
+                    PhaseBehaviour::RegenSpike {
+                        amount,
+                        interval,
+                        seconds,
+                    } => {
+                        commands.entity(entity).insert((
+                            health::Regen::new(amount, interval),
+                            RegenSpike {
+                                timer: Timer::from_seconds(seconds, TimerMode::Once),
+                                previous: previous.clone(),
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tick_speed_bursts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut SpeedBurst)>,
+) {
+    for (entity, mut burst) in query.iter_mut() {
+        burst.timer.tick(time.delta());
+        if burst.timer.finished() {
+            commands.entity(entity).remove::<SpeedBurst>();
+        }
+    }
+}
+
+pub fn tick_regen_spikes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut RegenSpike)>,
+) {
+    for (entity, mut spike) in query.iter_mut() {
+        spike.timer.tick(time.delta());
+        if spike.timer.finished() {
+            let mut entity = commands.entity(entity);
+            entity.remove::<RegenSpike>();
+            match spike.previous.take() {
+                Some(regen) => {
+                    entity.insert(regen);
+                }
+                None => {
+                    entity.remove::<health::Regen>();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+pub struct DamagePlugin;
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DamageKind>()
+            .register_type::<Damage>()
+            .register_type::<Critical>()
+            .register_type::<Armor>()
+            .register_type::<Resistances>();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, FromReflect, Hash, PartialEq, Reflect)]
+pub enum DamageKind {
+    #[default]
+    Physical,
+    Fire,
+    Frost,
+    /// ignores armor
+    Pierce,
+}
+
+/// damage dealt by a tower (copied onto each orb it fires)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Damage {
+    pub amount: usize,
+    pub kind: DamageKind,
+    /// extra fraction of damage dealt to shields (1.0 doubles it)
+    pub shield_bonus: f32,
+}
+
+/// chance (0 to 1) for a hit to deal `multiplier` times its damage (copied onto each orb like `Damage`)
+#[derive(Clone, Component, Debug, Default, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct Critical {
+    pub chance: f32,
+    pub multiplier: f32,
+}
+
+impl Critical {
+    /// returns whether the hit was a crit
+    pub fn roll(&self, damage: &mut Damage) -> bool {
+        if rand::thread_rng().gen::<f32>() >= self.chance {
+            return false;
+        }
+        damage.amount = (damage.amount as f32 * self.multiplier).round() as usize;
+        true
+    }
+}
+
+/// flat reduction first, then a percentage (0 to 1) of what is left
+#[derive(Clone, Component, Debug, Default, Deserialize, Reflect)]
+#[reflect(Component)]
+pub struct Armor {
+    #[serde(default)]
+    pub flat: usize,
+    #[serde(default)]
+    pub percent: f32,
+}
+
+/// percentage (0 to 1) of each kind of damage that is ignored (negative values are weaknesses)
+#[derive(Clone, Component, Debug, Default, Deserialize, Reflect)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct Resistances {
+    pub physical: f32,
+    pub fire: f32,
+    pub frost: f32,
+    pub pierce: f32,
+}
+
+impl Resistances {
+    pub fn get(&self, kind: DamageKind) -> f32 {
+        match kind {
+            DamageKind::Physical => self.physical,
+            DamageKind::Fire => self.fire,
+            DamageKind::Frost => self.frost,
+            DamageKind::Pierce => self.pierce,
+        }
+    }
+}
+
+/// the single place raw damage is turned into hp lost
+pub fn resolve_damage(
+    damage: &Damage,
+    armor: Option<&Armor>,
+    resistances: Option<&Resistances>,
+) -> usize {
+    let mut amount = damage.amount as f32;
+    if let Some(armor) = armor {
+        if damage.kind != DamageKind::Pierce {
+            amount = (amount - armor.flat as f32).max(0.) * (1. - armor.percent.clamp(0., 1.));
+        }
+    }
+    if let Some(resistances) = resistances {
+        amount *= 1. - resistances.get(damage.kind).min(1.);
+    }
+    amount.round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damage(amount: usize, kind: DamageKind) -> Damage {
+        Damage {
+            amount,
+            kind,
+            shield_bonus: 0.,
+        }
+    }
+
+    #[test]
+    fn flat_armor_comes_off_before_percent() {
+        let armor = Armor {
+            flat: 4,
+            percent: 0.5,
+        };
+        let hit = damage(20, DamageKind::Physical);
+        assert_eq!(resolve_damage(&hit, Some(&armor), None), 8);
+        assert_eq!(resolve_damage(&hit, None, None), 20);
+    }
+
+    #[test]
+    fn pierce_ignores_armor_but_not_resistances() {
+        let armor = Armor {
+            flat: 10,
+            percent: 0.9,
+        };
+        let resistances = Resistances {
+            pierce: 0.25,
+            ..Default::default()
+        };
+        let hit = damage(20, DamageKind::Pierce);
+        assert_eq!(resolve_damage(&hit, Some(&armor), None), 20);
+        assert_eq!(resolve_damage(&hit, Some(&armor), Some(&resistances)), 15);
+    }
+
+    #[test]
+    fn resistances_and_weaknesses_apply_per_kind() {
+        let resistances = Resistances {
+            fire: 0.5,
+            frost: -0.5,
+            ..Default::default()
+        };
+        let resolve = |kind| resolve_damage(&damage(10, kind), None, Some(&resistances));
+        assert_eq!(resolve(DamageKind::Fire), 5);
+        assert_eq!(resolve(DamageKind::Frost), 15);
+        assert_eq!(resolve(DamageKind::Physical), 10);
+    }
+
+    #[test]
+    fn damage_never_goes_below_zero() {
+        let armor = Armor {
+            flat: 50,
+            percent: 2.,
+        };
+        let immune = Resistances {
+            physical: 1.5,
+            ..Default::default()
+        };
+        let hit = damage(10, DamageKind::Physical);
+        assert_eq!(resolve_damage(&hit, Some(&armor), None), 0);
+        assert_eq!(resolve_damage(&hit, None, Some(&immune)), 0);
+        let armor = Armor {
+            flat: 0,
+            percent: -1.,
+        };
+        // negative armor doesn't add damage either
+        assert_eq!(resolve_damage(&hit, Some(&armor), None), 10);
+    }
+}
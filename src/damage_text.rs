@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::assets;
+use crate::camera;
+use crate::damage;
+use crate::health;
+
+/// texts are reused round-robin so a busy wave recycles the oldest numbers instead of spawning more
+const POOL_SIZE: usize = 128;
+const LIFETIME: f32 = 0.8;
+/// world units per second
+const RISE_SPEED: f32 = 1.5;
+const FONT_SIZE: f32 = 16.;
+const CRIT_FONT_SIZE: f32 = 26.;
+
+pub struct DamageTextPlugin;
+impl Plugin for DamageTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DamageText>()
+            .add_startup_system(spawn_damage_text_pool)
+            .add_system(show_damage_text)
+            .add_system(animate_damage_text.after(show_damage_text));
+    }
+}
+
+/// floating number that rises from `position` and fades out
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct DamageText {
+    pub position: Vec3,
+    pub timer: Timer,
+}
+
+#[derive(Resource, Default)]
+pub struct DamageTextPool {
+    pub texts: Vec<Entity>,
+    pub next: usize,
+}
+
+fn kind_color(kind: damage::DamageKind) -> Color {
+    match kind {
+        damage::DamageKind::Physical => Color::rgb(1., 1., 1.),
+        damage::DamageKind::Fire => Color::rgb(1., 0.5, 0.1),
+        damage::DamageKind::Frost => Color::rgb(0.5, 0.8, 1.),
+        damage::DamageKind::Pierce => Color::rgb(0.6, 1., 0.3),
+    }
+}
+
+pub fn spawn_damage_text_pool(mut commands: Commands, game_assets: Res<assets::GameAssets>) {
+    let texts = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn((
+                    TextBundle {
+                        text: Text::from_section(
+                            "",
+                            TextStyle {
+                                font: game_assets.font.clone(),
+                                font_size: FONT_SIZE,
+                                color: Color::WHITE,
+                            },
+                        ),
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..Default::default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..Default::default()
+                    },
+                    DamageText::default(),
+                    Name::new("DamageText"),
+                ))
+                .id()
+        })
+        .collect();
+    commands.insert_resource(DamageTextPool { texts, next: 0 });
+}
+
+pub fn show_damage_text(
+    mut pool: ResMut<DamageTextPool>,
+    mut dealt_events: EventReader<health::DamageDealt>,
+    targets: Query<&Transform>,
+    mut texts: Query<(&mut DamageText, &mut Text, &mut Visibility)>,
+) {
+    let rng = &mut rand::thread_rng();
+    for ev in dealt_events.iter() {
+        if ev.amount == 0 {
+            continue;
+        }
+        let Ok(target) = targets.get(ev.target) else {
+            continue;
+        };
+        let entity = pool.texts[pool.next];
+        pool.next = (pool.next + 1) % pool.texts.len();
+        let Ok((mut damage_text, mut text, mut visibility)) = texts.get_mut(entity) else {
+            continue;
+        };
+        damage_text.position =
+            target.translation + Vec3::new(rng.gen_range(-0.3..0.3), 0.5, rng.gen_range(-0.3..0.3));
+        damage_text.timer = Timer::from_seconds(LIFETIME, TimerMode::Once);
+        let section = &mut text.sections[0];
+        section.style.color = kind_color(ev.kind);
+        if ev.critical {
+            section.value = format!("{}!", ev.amount);
+            section.style.font_size = CRIT_FONT_SIZE;
+        } else {
+            section.value = ev.amount.to_string();
+            section.style.font_size = FONT_SIZE;
+        }
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// rise, fade and follow the camera (viewport coordinates start at the bottom left)
+pub fn animate_damage_text(
+    time: Res<Time>,
+    camera: Query<(&Camera, &GlobalTransform), With<camera::CameraPlayer>>,
+    mut texts: Query<(&mut DamageText, &mut Text, &mut Style, &mut Visibility)>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for (mut damage_text, mut text, mut style, mut visibility) in texts.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        damage_text.timer.tick(time.delta());
+        if damage_text.timer.finished() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        damage_text.position.y += RISE_SPEED * time.delta_seconds();
+        let Some(screen) = camera.world_to_viewport(camera_transform, damage_text.position) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        style.position.left = Val::Px(screen.x);
+        style.position.bottom = Val::Px(screen.y);
+        text.sections[0]
+            .style
+            .color
+            .set_a(damage_text.timer.percent_left());
+    }
+}
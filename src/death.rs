@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::enemy;
+use crate::health;
+use crate::pickup;
+use crate::wave;
+
+/// what happens when an enemy with this component dies
+#[derive(Clone, Component, Default)]
+pub struct OnDeath {
+    pub behaviours: Vec<DeathBehaviour>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum DeathBehaviour {
+    /// spawn `count` enemies of another archetype where this one died
+    Split { archetype: String, count: usize },
+    /// leave a pickup behind (with a `chance` between 0 and 1)
+    DropPickup { gold: usize, chance: f32 },
+    /// heal every other enemy within `radius`
+    HealNearby { radius: f32, hp: usize },
+}
+
+pub fn trigger_on_death(
+    mut commands: Commands,
+    mut died_events: EventReader<health::EntityDied>,
+    on_death_query: Query<(&OnDeath, &Transform, Option<&wave::WaveMember>)>,
+    mut enemy_query: Query<(Entity, &Transform, &mut health::HealthPoints), With<enemy::Enemy>>,
+    pickup_assets: Res<pickup::PickupAssets>,
+    mut spawn_events: EventWriter<enemy::SpawnEnemy>,
+) {
+    let rng = &mut rand::thread_rng();
+    for ev in died_events.iter() {
+        let Ok((on_death, death_transform, member)) = on_death_query.get(ev.entity) else {
+            continue;
+        };
+        let position = death_transform.translation;
+        let ground = Vec3::new(position.x, 0., position.z);
+        for behaviour in on_death.behaviours.iter() {
+            match behaviour {
+                DeathBehaviour::Split { archetype, count } => {
+                    for _ in 0..*count {
+                        let (jitter_x, jitter_z) =
+                            (rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5));
+                        spawn_events.send(enemy::SpawnEnemy {
+                            archetype: archetype.clone(),
+                            position: ground + Vec3::new(jitter_x, 0., jitter_z),
+                            wave: member.map(|member| member.wave),
+                        });
+                    }
+                }
+                DeathBehaviour::DropPickup { gold, chance } => {
+                    if rng.gen::<f32>() < *chance {
+                        pickup::spawn_pickup(&mut commands, &pickup_assets, ground, *gold);
+                    }
+                }
+                DeathBehaviour::HealNearby { radius, hp } => {
+                    for (entity, transform, mut health) in enemy_query.iter_mut() {
+                        if entity == ev.entity
+                            || health.hp == 0
+                            || (transform.translation - position).length() > *radius
+                        {
+                            continue;
+                        }
+                        health.hp = (health.hp + hp).min(health.max_hp);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+
+use crate::assets;
+use crate::enemy;
+use crate::health;
+use crate::pickup;
+use crate::placement;
+use crate::wave;
+
+const STARTING_GOLD: usize = 250;
+/// fraction of the gold held that is paid out when a wave is cleared
+const INTEREST_RATE: f32 = 0.1;
+const MAX_INTEREST: usize = 50;
+
+pub struct EconomyPlugin;
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Wallet>()
+            .add_event::<GoldEarned>()
+            .add_event::<GoldSpent>()
+            .add_startup_system(spawn_gold_text)
+            // bounties have to be read before dead enemies are despawned at the end of the frame
+            .add_system(
+                pay_bounties
+                    .after(health::apply_damage)
+                    .after(health::regen_health),
+            )
+            .add_system(pay_interest)
+            .add_system(collect_income)
+            .add_system(
+                add_earnings
+                    .after(pay_bounties)
+                    .after(pay_interest)
+                    .after(collect_income),
+            )
+            .add_system(log_economy)
+            .add_system(update_gold_text);
+    }
+}
+
+#[derive(Resource)]
+pub struct Wallet {
+    pub gold: usize,
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self {
+            gold: STARTING_GOLD,
+        }
+    }
+}
+
+impl Wallet {
+    /// takes `cost` out of the wallet if there is enough gold
+    pub fn try_spend(&mut self, cost: usize) -> bool {
+        if self.gold < cost {
+            return false;
+        }
+        self.gold -= cost;
+        true
+    }
+
+    pub fn interest(&self) -> usize {
+        ((self.gold as f32 * INTEREST_RATE) as usize).min(MAX_INTEREST)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Income {
+    Bounty,
+    Interest,
+    Pickup,
+    Sale,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Purchase {
+    Tower,
+    Upgrade,
+    Wall,
+}
+
+/// gold about to be added to the wallet (by `add_earnings`)
+pub struct GoldEarned {
+    pub gold: usize,
+    pub source: Income,
+}
+
+/// gold already taken out of the wallet
+pub struct GoldSpent {
+    pub gold: usize,
+    pub on: Purchase,
+}
+
+#[derive(Component)]
+pub struct GoldText;
+
+/// pays out for enemies something killed (not ones that died on their own, e.g. from decay)
+pub fn pay_bounties(
+    mut died_events: EventReader<health::EntityDied>,
+    bounties: Query<&enemy::Bounty>,
+    mut earned_events: EventWriter<GoldEarned>,
+) {
+    for ev in died_events.iter() {
+        if ev.killer.is_none() {
+            continue;
+        }
+        if let Ok(bounty) = bounties.get(ev.entity) {
+            earned_events.send(GoldEarned {
+                gold: bounty.gold,
+                source: Income::Bounty,
+            });
+        }
+    }
+}
+
+pub fn pay_interest(
+    wallet: Res<Wallet>,
+    mut cleared_events: EventReader<wave::WaveCleared>,
+    mut earned_events: EventWriter<GoldEarned>,
+) {
+    for _ in cleared_events.iter() {
+        earned_events.send(GoldEarned {
+            gold: wallet.interest(),
+            source: Income::Interest,
+        });
+    }
+}
+
+pub fn collect_income(
+    mut pickup_events: EventReader<pickup::PickupCollected>,
+    mut sold_events: EventReader<placement::TowerSold>,
+    mut earned_events: EventWriter<GoldEarned>,
+) {
+    for ev in pickup_events.iter() {
+        earned_events.send(GoldEarned {
+            gold: ev.gold,
+            source: Income::Pickup,
+        });
+    }
+    for ev in sold_events.iter() {
+        earned_events.send(GoldEarned {
+            gold: ev.refund,
+            source: Income::Sale,
+        });
+    }
+}
+
+pub fn add_earnings(mut wallet: ResMut<Wallet>, mut earned_events: EventReader<GoldEarned>) {
+    for ev in earned_events.iter() {
+        wallet.gold += ev.gold;
+    }
+}
+
+pub fn log_economy(
+    mut earned_events: EventReader<GoldEarned>,
+    mut spent_events: EventReader<GoldSpent>,
+) {
+    for ev in earned_events.iter() {
+        debug!("earned {} gold ({:?})", ev.gold, ev.source);
+    }
+    for ev in spent_events.iter() {
+        debug!("spent {} gold ({:?})", ev.gold, ev.on);
+    }
+}
+
+pub fn spawn_gold_text(mut commands: Commands, game_assets: Res<assets::GameAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: game_assets.font.clone(),
+                font_size: 22.,
+                color: Color::rgb(1., 0.8, 0.),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                right: Val::Px(10.),
+                top: Val::Px(10.),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        GoldText,
+        Name::new("GoldText"),
+    ));
+}
+
+pub fn update_gold_text(wallet: Res<Wallet>, mut query: Query<&mut Text, With<GoldText>>) {
+    if !wallet.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("{} gold", wallet.gold);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spending_needs_enough_gold() {
+        let mut wallet = Wallet { gold: 100 };
+        assert!(!wallet.try_spend(150));
+        assert_eq!(wallet.gold, 100);
+        assert!(wallet.try_spend(60));
+        assert_eq!(wallet.gold, 40);
+    }
+
+    fn bounties_paid(killer: impl Fn(&mut World) -> Option<Entity>) -> Vec<usize> {
+        let mut world = World::new();
+        world.init_resource::<Events<health::EntityDied>>();
+        world.init_resource::<Events<GoldEarned>>();
+        let enemy = world.spawn(enemy::Bounty { gold: 5 }).id();
+        let killer = killer(&mut world);
+        world.send_event(health::EntityDied {
+            entity: enemy,
+            killer,
+        });
+        let mut schedule = Schedule::new();
+        schedule.add_system(pay_bounties);
+        schedule.run(&mut world);
+        let earned = world.resource::<Events<GoldEarned>>();
+        earned.get_reader().iter(earned).map(|ev| ev.gold).collect()
+    }
+
+    #[test]
+    fn bounties_need_a_killer() {
+        assert_eq!(bounties_paid(|world| Some(world.spawn_empty().id())), vec![5]);
+        assert!(bounties_paid(|_| None).is_empty());
+    }
+
+    #[test]
+    fn interest_is_capped() {
+        assert_eq!(Wallet { gold: 120 }.interest(), 12);
+        assert_eq!(Wallet { gold: 5000 }.interest(), MAX_INTEREST);
+    }
+}
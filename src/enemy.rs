@@ -1,82 +1,386 @@
 use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
 use rand::Rng;
 
+use crate::assets;
+use crate::boss;
 use crate::camera;
+use crate::damage;
+use crate::death;
+use crate::flow_field;
+use crate::goal;
 use crate::health;
 use crate::orb;
 use crate::quad_tree;
-use crate::tower;
+use crate::status;
+use crate::wave;
 
 pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Enemy>()
-            .add_system(spawn_enemy)
-            .add_system(enemy_move);
+            .register_type::<Speed>()
+            .register_type::<Velocity>()
+            .register_type::<Bounty>()
+            .register_type::<LivesCost>()
+            .register_type::<Dash>()
+            .register_type::<Flying>()
+            .add_asset::<EnemyArchetypes>()
+            .add_asset_loader(assets::RonAssetLoader::<EnemyArchetypes>::new(&["enemies.ron"]))
+            .add_event::<SpawnEnemy>()
+            .add_system(spawn_enemy.run_if(in_state(goal::GameState::Playing)))
+            .add_system(spawn_requested_enemies)
+            .add_system(dash)
+            .add_system(enemy_move.run_if(in_state(goal::GameState::Playing)));
     }
 }
 
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
-pub struct Enemy;
+pub struct Enemy {
+    pub archetype: String,
+}
+
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Speed {
+    pub speed: f32,
+}
+
+/// how far the enemy moved per second last frame (used by towers to lead their shots)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Velocity {
+    pub velocity: Vec3,
+}
+
+/// gold paid out when the enemy is killed
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Bounty {
+    pub gold: usize,
+}
+
+/// lives taken from the player if the enemy reaches the goal
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct LivesCost {
+    pub lives: usize,
+}
+
+/// flies at `altitude` straight to the goal (only anti-air towers can target it)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Flying {
+    pub altitude: f32,
+}
+
+/// periodic burst of speed
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Dash {
+    pub multiplier: f32,
+    pub duration: Timer,
+    pub cooldown: Timer,
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        !self.duration.finished()
+    }
+}
+
+/// every enemy type keyed by id, loaded from a `*.enemies.ron` file
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "1471073d-1514-4f4b-b508-268e031ff1dc"]
+pub struct EnemyArchetypes {
+    pub archetypes: HashMap<String, EnemyArchetype>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EnemyArchetype {
+    pub shape: EnemyShape,
+    pub size: f32,
+    pub color: [f32; 3],
+    pub hp: usize,
+    #[serde(default)]
+    pub regen: Option<RegenDefinition>,
+    pub speed: f32,
+    #[serde(default)]
+    pub armor: Option<damage::Armor>,
+    #[serde(default)]
+    pub resistances: Option<damage::Resistances>,
+    /// absorbs damage before plating and hp and recharges after a delay
+    #[serde(default)]
+    pub shield: Option<ShieldDefinition>,
+    /// extra hp that is lost before hp (pierce damage goes straight through it)
+    #[serde(default)]
+    pub plating: Option<usize>,
+    #[serde(default)]
+    pub bounty: usize,
+    /// lives lost if the enemy reaches the goal
+    #[serde(default = "default_lives_cost")]
+    pub lives_cost: usize,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    /// height the enemy flies at (ground enemies leave this out)
+    #[serde(default)]
+    pub altitude: Option<f32>,
+    /// makes the enemy a boss that changes behaviour at hp thresholds
+    #[serde(default)]
+    pub phases: Vec<boss::BossPhase>,
+    #[serde(default)]
+    pub on_death: Vec<death::DeathBehaviour>,
+}
+
+fn default_lives_cost() -> usize {
+    1
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum EnemyShape {
+    Cube,
+    Sphere,
+    Capsule,
+}
+
+impl EnemyShape {
+    fn mesh(&self, size: f32) -> Mesh {
+        match self {
+            EnemyShape::Cube => Mesh::from(shape::Cube { size }),
+            EnemyShape::Sphere => Mesh::from(shape::UVSphere {
+                radius: size / 2.,
+                ..Default::default()
+            }),
+            EnemyShape::Capsule => Mesh::from(shape::Capsule {
+                radius: size / 4.,
+                depth: size / 2.,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegenDefinition {
+    pub amount: health::RegenAmount,
+    pub seconds: f32,
+    /// seconds regen pauses for after taking damage
+    #[serde(default)]
+    pub delay: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShieldDefinition {
+    pub hp: usize,
+    /// seconds without taking damage before the shield recharges
+    pub delay: f32,
+    /// hp restored per second
+    pub recharge: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum Ability {
+    Dash {
+        multiplier: f32,
+        duration: f32,
+        cooldown: f32,
+    },
+}
+
+/// request for an enemy to be spawned (used by the wave manager and the debug key)
+pub struct SpawnEnemy {
+    pub archetype: String,
+    pub position: Vec3,
+    /// the wave the enemy counts towards (none for enemies spawned by hand)
+    pub wave: Option<usize>,
+}
 
 pub fn spawn_enemy(
     keys: Res<Input<KeyCode>>,
-    mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
     camera: Query<&camera::CameraLookAt>,
+    mut spawn_events: EventWriter<SpawnEnemy>,
 ) {
     let camera = camera.iter().next().unwrap();
-    let location = camera.target + Vec3::new(0.0, 0.5, 0.);
+    let location = camera.target;
     let rng = &mut rand::thread_rng();
-    for key in keys.get_just_pressed() {
-        match key {
-            KeyCode::X => {
-                for _ in 0..10 {
-                    let (jitter_x, jitter_z) = (rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0));
-                    let loc = location + Vec3::new(jitter_x, 0., jitter_z);
-                    commands.spawn((
-                        PbrBundle {
-                            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.75 })),
-                            material: materials.add(StandardMaterial {
-                                base_color: Color::rgb(0.8, 0.1, 0.1).into(),
-                                alpha_mode: AlphaMode::Blend,
-                                ..Default::default()
-                            }),
-                            transform: Transform::from_translation(loc)
-                                .looking_at(Vec3::new(0., 0.5, 0.), Vec3::Y),
-                            ..Default::default()
-                        },
-                        health::HealthPoints { hp: 20, max_hp: 20 },
-                        health::Regen {
-                            hp: 1,
-                            timer: Timer::from_seconds(2., TimerMode::Repeating),
-                        },
-                        Enemy,
-                        Name::new("Enemy"),
-                    ));
+    if keys.just_pressed(KeyCode::X) {
+        for _ in 0..10 {
+            let (jitter_x, jitter_z) = (rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0));
+            spawn_events.send(SpawnEnemy {
+                archetype: "basic".to_string(),
+                position: location + Vec3::new(jitter_x, 0., jitter_z),
+                wave: None,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_requested_enemies(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnEnemy>,
+    game_assets: Res<assets::GameAssets>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    mut enemy_meshes: Local<HashMap<String, Handle<Mesh>>>,
+    mut enemy_materials: Local<HashMap<String, Handle<StandardMaterial>>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let Some(archetypes) = archetypes.get(&game_assets.enemies) else {
+        return;
+    };
+    for ev in spawn_events.iter() {
+        let Some(archetype) = archetypes.archetypes.get(&ev.archetype) else {
+            warn!("unknown enemy archetype {:?}", ev.archetype);
+            continue;
+        };
+        let mesh = enemy_meshes
+            .entry(ev.archetype.clone())
+            .or_insert_with(|| meshes.add(archetype.shape.mesh(archetype.size)))
+            .clone();
+        let material = enemy_materials
+            .entry(ev.archetype.clone())
+            .or_insert_with(|| {
+                let [r, g, b] = archetype.color;
+                materials.add(Color::rgb(r, g, b).into())
+            })
+            .clone();
+        let height = match archetype.altitude {
+            Some(altitude) => altitude,
+            None => archetype.size.max(0.5) / 2. + 0.125,
+        };
+        let loc = Vec3::new(ev.position.x, height, ev.position.z);
+        let mut enemy = commands.spawn((
+            PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(loc)
+                    .looking_at(Vec3::new(0., loc.y, 0.), Vec3::Y),
+                ..Default::default()
+            },
+            health::HealthPoints {
+                hp: archetype.hp,
+                max_hp: archetype.hp,
+            },
+            Speed {
+                speed: archetype.speed,
+            },
+            Velocity::default(),
+            LivesCost {
+                lives: archetype.lives_cost,
+            },
+            status::StatusEffects::default(),
+            Enemy {
+                archetype: ev.archetype.clone(),
+            },
+            Name::new("Enemy"),
+        ));
+        if let Some(wave) = ev.wave {
+            enemy.insert(wave::WaveMember { wave });
+        }
+        if let Some(regen) = &archetype.regen {
+            enemy.insert(health::Regen::new(regen.amount, regen.seconds).with_delay(regen.delay));
+        }
+        if let Some(altitude) = archetype.altitude {
+            enemy.insert(Flying { altitude });
+        }
+        if let Some(armor) = &archetype.armor {
+            enemy.insert(armor.clone());
+        }
+        if let Some(resistances) = &archetype.resistances {
+            enemy.insert(resistances.clone());
+        }
+        if let Some(shield) = &archetype.shield {
+            enemy.insert(health::Shield::new(shield.hp, shield.delay, shield.recharge));
+        }
+        if let Some(plating) = archetype.plating {
+            enemy.insert(health::Plating {
+                hp: plating,
+                max_hp: plating,
+            });
+        }
+        if archetype.bounty > 0 {
+            enemy.insert(Bounty {
+                gold: archetype.bounty,
+            });
+        }
+        if !archetype.on_death.is_empty() {
+            enemy.insert(death::OnDeath {
+                behaviours: archetype.on_death.clone(),
+            });
+        }
+        if !archetype.phases.is_empty() {
+            enemy.insert(boss::Boss::new(archetype.phases.clone()));
+        }
+        for ability in archetype.abilities.iter() {
+            match ability {
+                Ability::Dash {
+                    multiplier,
+                    duration,
+                    cooldown,
+                } => {
+                    let mut duration = Timer::from_seconds(*duration, TimerMode::Once);
+                    duration.tick(duration.duration());
+                    enemy.insert(Dash {
+                        multiplier: *multiplier,
+                        duration,
+                        cooldown: Timer::from_seconds(*cooldown, TimerMode::Repeating),
+                    });
                 }
             }
-            _ => {}
         }
     }
 }
 
-pub fn enemy_move(time: Res<Time>, mut query: Query<(&mut Transform, Entity), With<Enemy>>) {
+pub fn dash(time: Res<Time>, mut query: Query<&mut Dash>) {
+    for mut dash in query.iter_mut() {
+        dash.duration.tick(time.delta());
+        dash.cooldown.tick(time.delta());
+        if dash.cooldown.just_finished() {
+            dash.duration.reset();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn enemy_move(
+    time: Res<Time>,
+    flow_field: Res<flow_field::FlowField>,
+    goal_query: Query<&Transform, (With<goal::Goal>, Without<Enemy>)>,
+    mut query: Query<
+        (
+            &mut Transform,
+            Entity,
+            &Speed,
+            &mut Velocity,
+            Option<&Dash>,
+            Option<&boss::SpeedBurst>,
+            Option<&Flying>,
+            Option<&status::StatusEffects>,
+        ),
+        With<Enemy>,
+    >,
+) {
+    let goal = goal_query
+        .get_single()
+        .map(|t| t.translation)
+        .unwrap_or(Vec3::ZERO);
+
     // get all transforms and entities for referencing against
-    let vec_transforms: Vec<(Transform, Entity)> = query
+    let vec_transforms: Vec<(Transform, Entity, bool)> = query
         .iter()
-        .map(|t| { (*t.0, t.1)})
+        .map(|t| { (*t.0, t.1, t.6.is_some())})
         .collect();
 
     // calculate the new position for each enemy
-    for (mut t1, e1) in query.iter_mut() {
+    for (mut t1, e1, speed, mut velocity, dash, burst, flying, status) in query.iter_mut() {
         let (mut separation_direction, mut cohesion_direction) = (Vec3::ZERO, Vec3::ZERO);
-        for (t2, e2) in vec_transforms.iter() {
-            // skip self
-            if e1 == *e2 {
+        for (t2, e2, other_flying) in vec_transforms.iter() {
+            // skip self and anything on the other movement layer
+            if e1 == *e2 || flying.is_some() != *other_flying {
                 continue;
             }
             // separation
@@ -96,18 +400,46 @@ pub fn enemy_move(time: Res<Time>, mut query: Query<(&mut Transform, Entity), Wi
             // alignment
             // not needed for now since velocity is always the same
         }
-        // move towards the origin
-        let origin_direction = (Vec3::Y * 0.5 - t1.translation).normalize();
+        // ground enemies follow the flow field around walls, flying ones go straight for the goal
+        // (staying at the current height)
+        let position = Vec2::new(t1.translation.x, t1.translation.z);
+        let heading = match flow_field.route(position) {
+            flow_field::Route::Step(next) if flying.is_none() => next,
+            _ => Vec2::new(goal.x, goal.z),
+        };
+        let goal_direction =
+            (Vec3::new(heading.x, t1.translation.y, heading.y) - t1.translation).normalize_or_zero();
 
         // combine all the vectors 
         let move_vec = (
-            5. * origin_direction + 
+            5. * goal_direction + 
             1. * separation_direction + 
             1. * cohesion_direction
         ).normalize();
-        
+
+        let mut speed = speed.speed;
+        if let Some(dash) = dash {
+            if dash.is_dashing() {
+                speed *= dash.multiplier;
+            }
+        }
+        if let Some(burst) = burst {
+            speed *= burst.multiplier;
+        }
+        if let Some(status) = status {
+            speed *= status.speed_multiplier();
+        }
+
         // apply changes
-        t1.translation += move_vec.normalize() * 0.5 * time.delta_seconds();
+        velocity.velocity = move_vec.normalize() * speed;
+        let mut step = velocity.velocity * time.delta_seconds();
+        // ground enemies can't walk into walls, they slide along them instead
+        if flying.is_none() {
+            let walked = flow_field.walk(position, Vec2::new(step.x, step.z));
+            step = Vec3::new(walked.x, step.y, walked.y);
+            velocity.velocity = step.normalize_or_zero() * speed;
+        }
+        t1.translation += step;
     }
 }
 
@@ -137,61 +469,104 @@ pub fn index_enemies(
 }
 
 /// This is the main function that handles the collision detection and damage taking.
-/// The primary pattern being followed here (for components):
+/// Hits are sent on to `health::apply_damage` which resolves them and reports deaths.
+#[allow(clippy::single_match, clippy::type_complexity)]
 pub fn take_damage(
     mut commands: Commands,
     quad_tree: Res<quad_tree::QuadTree>,
-    mut enemy_query: Query<(Entity, &mut health::HealthPoints, &Transform), With<Enemy>>,
-    orb_query: Query<(Entity, &Transform, Option<&tower::Damage>), With<orb::Orb>>,
+    enemy_query: Query<(Entity, &Transform, Option<&health::Invulnerable>), With<Enemy>>,
+    orb_query: Query<
+        (
+            Entity,
+            &Transform,
+            &orb::Orb,
+            Option<&damage::Damage>,
+            Option<&damage::Critical>,
+            Option<&status::OnHit>,
+        ),
+        With<orb::Orb>,
+    >,
+    mut status_query: Query<&mut status::StatusEffects>,
+    mut hits: EventWriter<health::Hit>,
 ) {
-    let mut enemies_to_despawn = HashSet::new();
     let mut orbs_to_despawn = HashSet::new();
-    let mut enemies: HashMap<Entity, (&Transform, Mut<health::HealthPoints>)> = HashMap::new();
-    let mut orbs: HashMap<Entity, (&Transform, Option<&tower::Damage>)> = HashMap::new();
+    let mut enemies: HashMap<Entity, (&Transform, bool)> = HashMap::new();
+    let mut orbs: HashMap<
+        Entity,
+        (
+            &Transform,
+            &orb::Orb,
+            Option<&damage::Damage>,
+            Option<&damage::Critical>,
+            Option<&status::OnHit>,
+        ),
+    > = HashMap::new();
+    let mut status_hits: Vec<(Entity, Option<Entity>, &status::OnHit)> = Vec::new();
 
     enemy_query
-        .iter_mut()
-        .for_each(|(entity, healthpoints, transform)| {
-            enemies.insert(entity, (transform, healthpoints));
+        .iter()
+        .for_each(|(entity, transform, invulnerable)| {
+            enemies.insert(entity, (transform, invulnerable.is_some()));
+        });
+    orb_query
+        .iter()
+        .for_each(|(entity, transform, orb, damage, critical, on_hit)| {
+            orbs.insert(entity, (transform, orb, damage, critical, on_hit));
         });
-    orb_query.iter().for_each(|(entity, transform, damage)| {
-        orbs.insert(entity, (transform, damage));
-    });
 
     for node in quad_tree.get_leaf_nodes() {
         match node.objects {
             Some(obj_refs) => {
-                for (enemy, (enemy_transform, enemy_hp)) in
-                    enemies.iter_mut().filter(|(e, _)| obj_refs.contains(e))
+                for (enemy, (enemy_transform, invulnerable)) in
+                    enemies.iter().filter(|(e, _)| obj_refs.contains(e))
                 {
-                    for (orb, (orb_transform, damage)) in
-                        orbs.iter_mut().filter(|(o, _)| obj_refs.contains(o))
+                    for (orb_entity, (orb_transform, orb, damage, critical, on_hit)) in
+                        orbs.iter().filter(|(o, _)| obj_refs.contains(o))
                     {
+                        // each orb only ever hits one enemy
+                        if orbs_to_despawn.contains(orb_entity) {
+                            continue;
+                        }
                         let distance =
                             (orb_transform.translation - enemy_transform.translation).length();
                         if distance < 0.5 {
-                            orbs_to_despawn.insert(*orb);
+                            orbs_to_despawn.insert(*orb_entity);
 
                             // Depending on the effects of the orb, we can do different things here.
                             if let Some(damage) = damage {
-                                enemy_hp.hp = enemy_hp.hp.saturating_sub(damage.hp);
+                                let mut damage = (*damage).clone();
+                                let critical = match critical {
+                                    Some(critical) => critical.roll(&mut damage),
+                                    None => false,
+                                };
+                                hits.send(health::Hit {
+                                    source: orb.source,
+                                    target: *enemy,
+                                    damage,
+                                    critical,
+                                });
+                            }
+                            if let Some(on_hit) = on_hit {
+                                if !invulnerable {
+                                    status_hits.push((*enemy, orb.source, *on_hit));
+                                }
                             }
                         }
                     }
-
-                    if enemy_hp.hp == 0 {
-                        enemies_to_despawn.insert(*enemy);
-                    }
                 }
             }
             None => {}
         }
     }
 
-    for enemy in enemies_to_despawn {
-        commands.entity(enemy).despawn_recursive();
-    }
     for orb in orbs_to_despawn {
         commands.entity(orb).despawn_recursive();
     }
+    for (enemy, source, on_hit) in status_hits {
+        if let Ok(mut status) = status_query.get_mut(enemy) {
+            for effect in on_hit.effects.iter() {
+                status.apply(effect, source);
+            }
+        }
+    }
 }
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::enemy;
+use crate::goal;
+use crate::grid;
+use crate::wall;
+
+pub struct FlowFieldPlugin;
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowField>()
+            .add_system(update_flow_field.before(enemy::enemy_move));
+    }
+}
+
+/// which way a ground enemy should go from where it is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Route {
+    /// straight for the goal (it's in the goal's cell)
+    Goal,
+    /// towards the centre of the next cell on the shortest path
+    Step(Vec2),
+    /// walls cut it off from the goal, so it heads straight for it and breaks through
+    CutOff,
+}
+
+/// steps to the goal from every cell, avoiding walls (rebuilt whenever a wall goes up or down)
+#[derive(Resource, Default)]
+pub struct FlowField {
+    positions: HashMap<IVec2, Vec2>,
+    blocked: HashSet<IVec2>,
+    distances: HashMap<IVec2, usize>,
+}
+
+impl FlowField {
+    /// breadth first search out from the goal cell over `(coordinates, position, blocked)` cells
+    pub fn build(cells: impl Iterator<Item = (IVec2, Vec2, bool)>, goal: IVec2) -> Self {
+        let mut field = FlowField::default();
+        for (coordinates, position, blocked) in cells {
+            field.positions.insert(coordinates, position);
+            if blocked {
+                field.blocked.insert(coordinates);
+            }
+        }
+        if !field.positions.contains_key(&goal) || field.blocked.contains(&goal) {
+            return field;
+        }
+        let mut queue = VecDeque::from([goal]);
+        field.distances.insert(goal, 0);
+        while let Some(current) = queue.pop_front() {
+            let distance = field.distances[&current] + 1;
+            for neighbour in grid::neighbours(current) {
+                if !field.positions.contains_key(&neighbour)
+                    || field.blocked.contains(&neighbour)
+                    || field.distances.contains_key(&neighbour)
+                {
+                    continue;
+                }
+                field.distances.insert(neighbour, distance);
+                queue.push_back(neighbour);
+            }
+        }
+        field
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn is_blocked(&self, position: Vec2) -> bool {
+        self.blocked.contains(&grid::coordinates_of(position))
+    }
+
+    /// the part of `step` a ground enemy at `position` can take without walking into a wall:
+    /// all of it, just the part along one axis (sliding along the wall), or else towards the
+    /// centre of its cell, from where the next cell can be reached in a straight line
+    pub fn walk(&self, position: Vec2, step: Vec2) -> Vec2 {
+        // anything inside a wall is already on its way out
+        if self.is_blocked(position) || !self.is_blocked(position + step) {
+            return step;
+        }
+        for slide in [Vec2::new(step.x, 0.), Vec2::new(0., step.y)] {
+            if slide != Vec2::ZERO && !self.is_blocked(position + slide) {
+                return slide;
+            }
+        }
+        let centre = grid::position_of(grid::coordinates_of(position));
+        (centre - position).clamp_length_max(step.length())
+    }
+
+    /// how far a ground enemy at `position` still has to walk around the walls to the goal's
+    /// cell (none in the goal's cell itself, off the grid, or when walls cut it off)
+    pub fn path_distance(&self, position: Vec2) -> Option<f32> {
+        let cells = *self.distances.get(&grid::coordinates_of(position))?;
+        match self.route(position) {
+            Route::Step(next) => {
+                Some((next - position).length() + (cells - 1) as f32 * grid::ROW_SPACING)
+            }
+            Route::Goal | Route::CutOff => None,
+        }
+    }
+
+    pub fn route(&self, position: Vec2) -> Route {
+        let coordinates = grid::coordinates_of(position);
+        if !self.positions.contains_key(&coordinates) {
+            // off the grid: walk onto the nearest cell that still leads to the goal
+            return self
+                .distances
+                .keys()
+                .map(|c| self.positions[c])
+                .min_by(|a, b| {
+                    (*a - position)
+                        .length_squared()
+                        .total_cmp(&(*b - position).length_squared())
+                })
+                .map_or(Route::CutOff, Route::Step);
+        }
+        let distance = self.distances.get(&coordinates).copied();
+        if distance == Some(0) {
+            return Route::Goal;
+        }
+        // anything inside a wall just steps out of it
+        let best = grid::neighbours(coordinates)
+            .into_iter()
+            .filter_map(|n| self.distances.get(&n).map(|d| (n, *d)))
+            .min_by_key(|(_, d)| *d);
+        match (best, distance) {
+            (Some((next, _)), None) if self.blocked.contains(&coordinates) => {
+                Route::Step(self.positions[&next])
+            }
+            (Some((next, next_distance)), Some(distance)) if next_distance < distance => {
+                Route::Step(self.positions[&next])
+            }
+            _ => Route::CutOff,
+        }
+    }
+}
+
+pub fn update_flow_field(
+    mut field: ResMut<FlowField>,
+    added: Query<(), Added<wall::Wall>>,
+    mut removed: RemovedComponents<wall::Wall>,
+    cells: Query<&grid::Cell>,
+    walls: Query<&wall::Wall>,
+    goal: Query<&Transform, With<goal::Goal>>,
+) {
+    let walls_changed = !added.is_empty() | (removed.iter().count() > 0);
+    if !walls_changed && !field.is_empty() {
+        return;
+    }
+    let goal = goal
+        .get_single()
+        .map(|t| Vec2::new(t.translation.x, t.translation.z))
+        .unwrap_or(Vec2::ZERO);
+    let blocked: HashSet<IVec2> = walls.iter().map(|wall| wall.coordinates).collect();
+    *field = FlowField::build(
+        cells.iter().map(|cell| {
+            (
+                cell.coordinates,
+                cell.position,
+                blocked.contains(&cell.coordinates),
+            )
+        }),
+        grid::coordinates_of(goal),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(radius: i32, walls: &[IVec2]) -> FlowField {
+        let mut cells = vec![];
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let coordinates = IVec2::new(x, y);
+                cells.push((
+                    coordinates,
+                    grid::position_of(coordinates),
+                    walls.contains(&coordinates),
+                ));
+            }
+        }
+        FlowField::build(cells.into_iter(), IVec2::ZERO)
+    }
+
+    #[test]
+    fn steps_lead_to_the_goal() {
+        let field = field(3, &[]);
+        let mut position = grid::position_of(IVec2::new(3, 3));
+        for _ in 0..10 {
+            match field.route(position) {
+                Route::Step(next) => position = next,
+                route => {
+                    assert_eq!(route, Route::Goal);
+                    return;
+                }
+            }
+        }
+        panic!("never reached the goal");
+    }
+
+    #[test]
+    fn routes_around_walls() {
+        let start = IVec2::new(0, 2);
+        let field = field(3, &[IVec2::new(0, 1)]);
+        let Route::Step(next) = field.route(grid::position_of(start)) else {
+            panic!("expected a step");
+        };
+        assert_ne!(grid::coordinates_of(next), IVec2::new(0, 1));
+        assert!(!field.is_blocked(next));
+    }
+
+    #[test]
+    fn enemies_get_round_wall_corners() {
+        let field = field(4, &[IVec2::new(0, 2), IVec2::new(1, 1)]);
+        for start in [
+            Vec2::new(-0.92, 4.59),
+            Vec2::new(-1.15, 6.21),
+            Vec2::new(-0.92, 5.94),
+        ] {
+            let mut position = start;
+            let mut reached = false;
+            for _ in 0..2000 {
+                let heading = match field.route(position) {
+                    Route::Step(next) => next,
+                    Route::Goal => {
+                        reached = true;
+                        break;
+                    }
+                    Route::CutOff => panic!("the goal can be reached from {start}"),
+                };
+                // pushed a little off course (as by neighbouring enemies), so corners get clipped
+                let direction =
+                    Vec2::from_angle(0.4).rotate((heading - position).normalize_or_zero());
+                position += field.walk(position, direction * 0.05);
+                assert!(
+                    !field.is_blocked(position),
+                    "walked into a wall from {start}"
+                );
+            }
+            assert!(reached, "got stuck on the way from {start} at {position}");
+        }
+    }
+
+    #[test]
+    fn path_distance_counts_the_detour() {
+        let start = grid::position_of(IVec2::new(0, 3));
+        let open = field(4, &[]).path_distance(start).unwrap();
+        let walled = field(4, &[IVec2::new(-1, 2), IVec2::new(0, 2), IVec2::new(1, 2)])
+            .path_distance(start)
+            .unwrap();
+        assert!(walled > open + grid::ROW_SPACING);
+        assert_eq!(field(4, &[]).path_distance(grid::position_of(IVec2::ZERO)), None);
+    }
+
+    #[test]
+    fn enclosed_goal_cuts_enemies_off() {
+        let field = field(3, &grid::neighbours(IVec2::ZERO));
+        assert_eq!(
+            field.route(grid::position_of(IVec2::new(2, 2))),
+            Route::CutOff
+        );
+        // off the grid the only cell left to head for is the goal's
+        assert_eq!(
+            field.route(Vec2::new(30., 30.)),
+            Route::Step(grid::position_of(IVec2::ZERO))
+        );
+        assert_eq!(field.route(grid::position_of(IVec2::ZERO)), Route::Goal);
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::enemy;
+
+pub struct GoalPlugin;
+impl Plugin for GoalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Goal>()
+            .add_state::<GameState>()
+            .insert_resource(Lives { lives: 20 })
+            .add_event::<EnemyLeaked>()
+            .add_startup_system(spawn_goal)
+            .add_system(leak_enemies.run_if(in_state(GameState::Playing)))
+            .add_system(log_leaks)
+            .add_system(game_over.in_schedule(OnEnter(GameState::GameOver)));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, States)]
+pub enum GameState {
+    #[default]
+    Playing,
+    GameOver,
+}
+
+/// the base the enemies are walking towards
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Goal {
+    pub radius: f32,
+}
+
+#[derive(Resource)]
+pub struct Lives {
+    pub lives: usize,
+}
+
+pub struct EnemyLeaked {
+    pub entity: Entity,
+    pub archetype: String,
+    pub lives: usize,
+}
+
+pub fn spawn_goal(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let radius = 1.0;
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Torus {
+                radius,
+                ring_radius: 0.1,
+                ..Default::default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.1, 0.8, 0.2),
+                emissive: Color::rgb(0.0, 0.4, 0.1),
+                ..Default::default()
+            }),
+            transform: Transform::from_translation(Vec3::new(0., 0.2, 0.)),
+            ..Default::default()
+        },
+        Goal { radius },
+        Name::new("Goal"),
+    ));
+}
+
+/// remove enemies that reach the goal and take lives for each one
+pub fn leak_enemies(
+    mut commands: Commands,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+    goal_query: Query<(&Transform, &Goal)>,
+    enemy_query: Query<(Entity, &Transform, &enemy::Enemy, Option<&enemy::LivesCost>)>,
+    mut leaked_events: EventWriter<EnemyLeaked>,
+) {
+    for (goal_transform, goal) in goal_query.iter() {
+        let goal_position = Vec2::new(goal_transform.translation.x, goal_transform.translation.z);
+        for (entity, transform, enemy, cost) in enemy_query.iter() {
+            let position = Vec2::new(transform.translation.x, transform.translation.z);
+            if (position - goal_position).length() > goal.radius {
+                continue;
+            }
+            let cost = cost.map(|c| c.lives).unwrap_or(1);
+            lives.lives = lives.lives.saturating_sub(cost);
+            leaked_events.send(EnemyLeaked {
+                entity,
+                archetype: enemy.archetype.clone(),
+                lives: cost,
+            });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if lives.lives == 0 {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+pub fn log_leaks(lives: Res<Lives>, mut leaked_events: EventReader<EnemyLeaked>) {
+    for ev in leaked_events.iter() {
+        info!(
+            "{} ({:?}) leaked for {} lives, {} remaining",
+            ev.archetype, ev.entity, ev.lives, lives.lives
+        );
+    }
+}
+
+pub fn game_over() {
+    info!("game over");
+}
@@ -3,13 +3,66 @@ use bevy_mod_picking::PickableBundle;
 
 use crate::assets;
 
+/// distance between the centres of neighbouring rows (and of any two neighbouring cells)
+pub const ROW_SPACING: f32 = 1.732_050_8;
+const COLUMN_SPACING: f32 = 1.5;
+
 #[derive(Clone, Component, Default, Reflect)]
 #[reflect(Component)]
 pub struct Cell {
     pub position: Vec2,
+    /// column and row of the cell (every even column sits half a row further along z)
+    pub coordinates: IVec2,
     pub is_occupied: bool,
 }
 
+/// centre of the cell at `coordinates`
+pub fn position_of(coordinates: IVec2) -> Vec2 {
+    let mut z = coordinates.y as f32;
+    if coordinates.x % 2 == 0 {
+        z += 0.5;
+    }
+    Vec2::new(coordinates.x as f32 * COLUMN_SPACING, z * ROW_SPACING)
+}
+
+/// coordinates of the cell containing `position` (rounded in cube coordinates, so corners land right)
+pub fn coordinates_of(position: Vec2) -> IVec2 {
+    // axial coordinates, with the even columns' half row taken out
+    let q = position.x / COLUMN_SPACING;
+    let r = position.y / ROW_SPACING - 0.5 - q / 2.;
+    let s = -q - r;
+    let (mut column, mut axial_row, rounded_s) = (q.round(), r.round(), s.round());
+    let (q_diff, r_diff, s_diff) = (
+        (column - q).abs(),
+        (axial_row - r).abs(),
+        (rounded_s - s).abs(),
+    );
+    // the component that moved furthest is put back from the other two
+    if q_diff > r_diff && q_diff > s_diff {
+        column = -axial_row - rounded_s;
+    } else if r_diff > s_diff {
+        axial_row = -column - rounded_s;
+    }
+    let column = column as i32;
+    let row = axial_row as i32 + (column + column.rem_euclid(2)) / 2;
+    IVec2::new(column, row)
+}
+
+/// the six cells touching the cell at `coordinates`
+pub fn neighbours(coordinates: IVec2) -> [IVec2; 6] {
+    let IVec2 { x, y } = coordinates;
+    // the shifted (even) columns touch the next row of their neighbouring columns, the others the previous
+    let side = if x % 2 == 0 { y + 1 } else { y - 1 };
+    [
+        IVec2::new(x, y - 1),
+        IVec2::new(x, y + 1),
+        IVec2::new(x - 1, y),
+        IVec2::new(x + 1, y),
+        IVec2::new(x - 1, side),
+        IVec2::new(x + 1, side),
+    ]
+}
+
 pub fn spawn_grid(
     mut commands: Commands,
     assets: Res<assets::GameAssets>,
@@ -18,22 +71,18 @@ pub fn spawn_grid(
     let n = 8;
     for x in -n..(n + 1) {
         for z in -n..(n + 1) {
-            let mut z = z as f32;
-            // offset every other row
-            if x % 2 == 0 {
-                z += 0.5;
-            }
-            z = z as f32 * f32::sqrt(3.);
-            let x = x as f32 * 1.5;
+            let coordinates = IVec2::new(x, z);
+            let Vec2 { x, y: z } = position_of(coordinates);
             commands.spawn((
                 PbrBundle {
                     mesh: assets.hexagon_mesh.clone(),
                     material: materials.add(Color::rgb(0.1, 0.1, 0.1).into()),
-                    transform: Transform::from_translation(Vec3::new(x as f32, 0.0, z as f32)),
+                    transform: Transform::from_translation(Vec3::new(x, 0.0, z)),
                     ..Default::default()
                 },
                 Cell {
-                    position: Vec2::new(x as f32, z as f32),
+                    position: Vec2::new(x, z),
+                    coordinates,
                     is_occupied: false,
                 },
                 Name::new("Cell"),
@@ -42,3 +91,45 @@ pub fn spawn_grid(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_round_trip() {
+        for x in -3..4 {
+            for y in -3..4 {
+                let coordinates = IVec2::new(x, y);
+                assert_eq!(coordinates_of(position_of(coordinates)), coordinates);
+            }
+        }
+    }
+
+    #[test]
+    fn positions_map_to_the_nearest_cell() {
+        // next to the right hand corner of (0, 0), closer to its centre than to (1, 0) or (1, 1)
+        assert_eq!(coordinates_of(Vec2::new(0.8, 0.866)), IVec2::new(0, 0));
+        for i in -40..40 {
+            for j in -40..40 {
+                let position = Vec2::new(i as f32 * 0.13, j as f32 * 0.11);
+                let coordinates = coordinates_of(position);
+                let distance = (position_of(coordinates) - position).length();
+                for neighbour in neighbours(coordinates) {
+                    assert!(distance <= (position_of(neighbour) - position).length() + 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours_are_one_row_apart() {
+        for coordinates in [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(-1, 2)] {
+            let position = position_of(coordinates);
+            for neighbour in neighbours(coordinates) {
+                let distance = (position_of(neighbour) - position).length();
+                assert!((distance - ROW_SPACING).abs() < 1e-4);
+            }
+        }
+    }
+}
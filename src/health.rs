@@ -1,14 +1,25 @@
 use bevy::prelude::*;
+use serde::Deserialize;
+use std::time::Duration;
 
-use crate::enemy;
+use crate::damage;
 
 pub struct HealthPointsPlugin;
 impl Plugin for HealthPointsPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<HealthPoints>()
+            .register_type::<RegenAmount>()
             .register_type::<Regen>()
-            .add_system(regen_health)
-            .add_system(update_alpha);
+            .register_type::<Shield>()
+            .register_type::<Plating>()
+            .register_type::<Invulnerable>()
+            .add_event::<Hit>()
+            .add_event::<DamageDealt>()
+            .add_event::<EntityDied>()
+            .add_system(regen_health.after(apply_damage))
+            .add_system(recharge_shields.after(apply_damage))
+            .add_system(tick_invulnerable)
+            .add_system(log_damage);
     }
 }
 
@@ -19,31 +30,473 @@ pub struct HealthPoints {
     pub max_hp: usize,
 }
 
-#[derive(Clone, Component, Default, Reflect)]
+/// how much hp each regen tick restores (negative values decay hp instead)
+#[derive(Clone, Copy, Debug, Deserialize, FromReflect, PartialEq, Reflect)]
+pub enum RegenAmount {
+    /// hp per tick (can be fractional)
+    Flat(f32),
+    /// fraction of max hp per tick
+    Percent(f32),
+}
+
+impl Default for RegenAmount {
+    fn default() -> Self {
+        RegenAmount::Flat(0.)
+    }
+}
+
+#[derive(Clone, Component, Default, FromReflect, Reflect)]
 #[reflect(Component)]
 pub struct Regen {
+    pub amount: RegenAmount,
+    pub timer: Timer,
+    /// seconds regen stays paused after taking damage
+    pub delay: f32,
+    /// seconds since damage was last taken
+    pub since_damage: f32,
+    /// fractional hp carried over between ticks
+    pub accumulated: f32,
+}
+
+impl Regen {
+    pub fn new(amount: RegenAmount, seconds: f32) -> Self {
+        Self {
+            amount,
+            timer: Timer::from_seconds(seconds, TimerMode::Repeating),
+            delay: 0.,
+            since_damage: 0.,
+            accumulated: 0.,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self.since_damage = delay;
+        self
+    }
+
+    /// pause regen for `delay` seconds
+    pub fn interrupt(&mut self) {
+        self.since_damage = 0.;
+        self.timer.reset();
+    }
+
+    /// advance by `delta` and apply any ticks to `hp` (clamped between 0 and max)
+    pub fn tick(&mut self, delta: Duration, hp: &mut HealthPoints) {
+        // dead entities are left alone until they get despawned
+        if hp.hp == 0 {
+            return;
+        }
+        if self.since_damage < self.delay {
+            self.since_damage += delta.as_secs_f32();
+            return;
+        }
+        self.timer.tick(delta);
+        let ticks = self.timer.times_finished_this_tick();
+        if ticks == 0 {
+            return;
+        }
+        let per_tick = match self.amount {
+            RegenAmount::Flat(hp) => hp,
+            RegenAmount::Percent(fraction) => fraction * hp.max_hp as f32,
+        };
+        self.accumulated += per_tick * ticks as f32;
+        let whole = self.accumulated.trunc();
+        self.accumulated -= whole;
+        let new_hp = (hp.hp as f32 + whole).clamp(0., hp.max_hp as f32);
+        hp.hp = new_hp as usize;
+        // nothing left to store up once full (or empty)
+        if (hp.hp == hp.max_hp && self.accumulated > 0.) || hp.hp == 0 {
+            self.accumulated = 0.;
+        }
+    }
+}
+
+/// outer layer that absorbs damage first and recharges once it hasn't been hit for `delay` seconds
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Shield {
+    pub hp: usize,
+    pub max_hp: usize,
+    pub delay: f32,
+    /// hp restored per second while recharging
+    pub recharge: f32,
+    pub since_damage: f32,
+    pub accumulated: f32,
+}
+
+impl Shield {
+    pub fn new(max_hp: usize, delay: f32, recharge: f32) -> Self {
+        Self {
+            hp: max_hp,
+            max_hp,
+            delay,
+            recharge,
+            since_damage: delay,
+            accumulated: 0.,
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        if self.since_damage < self.delay {
+            self.since_damage += delta.as_secs_f32();
+            return;
+        }
+        if self.hp >= self.max_hp {
+            return;
+        }
+        self.accumulated += self.recharge * delta.as_secs_f32();
+        let whole = self.accumulated.trunc();
+        self.accumulated -= whole;
+        self.hp = (self.hp + whole as usize).min(self.max_hp);
+    }
+}
+
+/// middle layer between the shield and hp (doesn't recharge and is skipped by pierce damage)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Plating {
+    pub hp: usize,
+    pub max_hp: usize,
+}
+
+/// how much of a hit each layer took
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LayeredDamage {
+    pub shield: usize,
+    pub plating: usize,
     pub hp: usize,
+}
+
+impl LayeredDamage {
+    pub fn total(&self) -> usize {
+        self.shield + self.plating + self.hp
+    }
+}
+
+/// run already resolved damage (see `damage::resolve_damage`) through shield -> plating -> hp
+pub fn resolve_layers(
+    amount: usize,
+    damage: &damage::Damage,
+    shield: Option<&mut Shield>,
+    plating: Option<&mut Plating>,
+    hp: &mut HealthPoints,
+) -> LayeredDamage {
+    let mut dealt = LayeredDamage::default();
+    let mut remaining = amount as f32;
+    if let Some(shield) = shield {
+        let multiplier = 1. + damage.shield_bonus.max(0.);
+        let absorbed = ((remaining * multiplier).round() as usize).min(shield.hp);
+        shield.hp -= absorbed;
+        dealt.shield = absorbed;
+        remaining = (remaining - absorbed as f32 / multiplier).max(0.);
+    }
+    let mut remaining = remaining.round() as usize;
+    if let Some(plating) = plating {
+        if damage.kind != damage::DamageKind::Pierce {
+            let absorbed = remaining.min(plating.hp);
+            plating.hp -= absorbed;
+            dealt.plating = absorbed;
+            remaining -= absorbed;
+        }
+    }
+    dealt.hp = remaining.min(hp.hp);
+    hp.hp -= dealt.hp;
+    dealt
+}
+
+/// ignores all damage until the timer runs out
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Invulnerable {
     pub timer: Timer,
 }
 
-pub fn regen_health(time: Res<Time>, mut query: Query<(&mut HealthPoints, &mut Regen)>) {
-    for (mut hp, mut regen) in query.iter_mut() {
-        regen.timer.tick(time.delta());
-        if regen.timer.just_finished() && hp.hp < hp.max_hp {
-            hp.hp += regen.hp;
+/// raw damage headed for `target` (resolved against armor etc. by `apply_damage`)
+pub struct Hit {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub damage: damage::Damage,
+    pub critical: bool,
+}
+
+/// hp actually taken off `target` by a hit
+pub struct DamageDealt {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: usize,
+    pub kind: damage::DamageKind,
+    pub critical: bool,
+}
+
+/// sent once when an entity's hp reaches zero (the entity is despawned by `despawn_dead`)
+pub struct EntityDied {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+pub fn regen_health(
+    time: Res<Time>,
+    mut dealt_events: EventReader<DamageDealt>,
+    mut query: Query<(Entity, &mut HealthPoints, &mut Regen)>,
+    mut died_events: EventWriter<EntityDied>,
+) {
+    for ev in dealt_events.iter() {
+        if let Ok((_, _, mut regen)) = query.get_mut(ev.target) {
+            regen.interrupt();
+        }
+    }
+    for (entity, mut hp, mut regen) in query.iter_mut() {
+        let alive = hp.hp > 0;
+        regen.tick(time.delta(), &mut hp);
+        // decay can kill
+        if alive && hp.hp == 0 {
+            died_events.send(EntityDied {
+                entity,
+                killer: None,
+            });
+        }
+    }
+}
+
+pub fn recharge_shields(
+    time: Res<Time>,
+    mut dealt_events: EventReader<DamageDealt>,
+    mut query: Query<&mut Shield>,
+) {
+    for ev in dealt_events.iter() {
+        if let Ok(mut shield) = query.get_mut(ev.target) {
+            shield.since_damage = 0.;
+        }
+    }
+    for mut shield in query.iter_mut() {
+        shield.tick(time.delta());
+    }
+}
+
+pub fn tick_invulnerable(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        invulnerable.timer.tick(time.delta());
+        if invulnerable.timer.finished() {
+            commands.entity(entity).remove::<Invulnerable>();
         }
     }
 }
 
-pub fn update_alpha(
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    query: Query<(&HealthPoints, &Handle<StandardMaterial>), With<enemy::Enemy>>,
+#[allow(clippy::type_complexity)]
+pub fn apply_damage(
+    mut hits: EventReader<Hit>,
+    mut query: Query<(
+        &mut HealthPoints,
+        Option<&mut Shield>,
+        Option<&mut Plating>,
+        Option<&damage::Armor>,
+        Option<&damage::Resistances>,
+        Option<&Invulnerable>,
+    )>,
+    mut dealt_events: EventWriter<DamageDealt>,
+    mut died_events: EventWriter<EntityDied>,
 ) {
-    for (hp, material_handle) in query.iter() {
-        if let Some(material) = materials.get_mut(material_handle) {
-            material
-                .base_color
-                .set_a(hp.hp as f32 / (2. * hp.max_hp as f32) + 0.5);
+    for hit in hits.iter() {
+        let Ok((mut hp, shield, plating, armor, resistances, invulnerable)) =
+            query.get_mut(hit.target)
+        else {
+            continue;
+        };
+        // already dead (it gets despawned at the end of the frame) or shielded
+        if hp.hp == 0 || invulnerable.is_some() {
+            continue;
+        }
+        let amount = damage::resolve_damage(&hit.damage, armor, resistances);
+        let dealt = resolve_layers(
+            amount,
+            &hit.damage,
+            shield.map(|s| s.into_inner()),
+            plating.map(|p| p.into_inner()),
+            &mut hp,
+        );
+        dealt_events.send(DamageDealt {
+            source: hit.source,
+            target: hit.target,
+            amount: dealt.total(),
+            kind: hit.damage.kind,
+            critical: hit.critical,
+        });
+        if hp.hp == 0 {
+            died_events.send(EntityDied {
+                entity: hit.target,
+                killer: hit.source,
+            });
+        }
+    }
+}
+
+pub fn despawn_dead(mut commands: Commands, mut died_events: EventReader<EntityDied>) {
+    for ev in died_events.iter() {
+        if let Some(ec) = commands.get_entity(ev.entity) {
+            ec.despawn_recursive();
         }
     }
 }
+
+pub fn log_damage(
+    mut dealt_events: EventReader<DamageDealt>,
+    mut died_events: EventReader<EntityDied>,
+) {
+    for ev in dealt_events.iter() {
+        debug!(
+            "{:?} dealt {} {:?} damage to {:?}{}",
+            ev.source,
+            ev.amount,
+            ev.kind,
+            ev.target,
+            if ev.critical { " (crit)" } else { "" }
+        );
+    }
+    for ev in died_events.iter() {
+        debug!("{:?} killed by {:?}", ev.entity, ev.killer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(seconds: f32) -> Duration {
+        Duration::from_secs_f32(seconds)
+    }
+
+    #[test]
+    fn flat_regen_clamps_to_max() {
+        let mut hp = HealthPoints { hp: 19, max_hp: 20 };
+        let mut regen = Regen::new(RegenAmount::Flat(5.), 1.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 20);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 20);
+    }
+
+    #[test]
+    fn fractional_regen_accumulates() {
+        let mut hp = HealthPoints { hp: 10, max_hp: 20 };
+        let mut regen = Regen::new(RegenAmount::Flat(0.5), 1.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 10);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 11);
+        regen.tick(secs(2.), &mut hp);
+        assert_eq!(hp.hp, 12);
+    }
+
+    #[test]
+    fn percent_regen_scales_with_max_hp() {
+        let mut hp = HealthPoints {
+            hp: 50,
+            max_hp: 200,
+        };
+        let mut regen = Regen::new(RegenAmount::Percent(0.05), 1.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 60);
+    }
+
+    #[test]
+    fn regen_pauses_after_damage() {
+        let mut hp = HealthPoints { hp: 10, max_hp: 20 };
+        let mut regen = Regen::new(RegenAmount::Flat(1.), 1.).with_delay(3.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 11);
+
+        regen.interrupt();
+        regen.tick(secs(1.), &mut hp);
+        regen.tick(secs(1.), &mut hp);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 11);
+
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 12);
+    }
+
+    #[test]
+    fn negative_regen_decays_to_zero() {
+        let mut hp = HealthPoints { hp: 3, max_hp: 20 };
+        let mut regen = Regen::new(RegenAmount::Flat(-2.), 1.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 1);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 0);
+    }
+
+    #[test]
+    fn damage_goes_through_shield_then_plating_then_hp() {
+        let damage = damage::Damage {
+            amount: 0,
+            kind: damage::DamageKind::Physical,
+            shield_bonus: 0.,
+        };
+        let mut shield = Shield::new(5, 2., 1.);
+        let mut plating = Plating { hp: 5, max_hp: 5 };
+        let mut hp = HealthPoints { hp: 20, max_hp: 20 };
+        let dealt = resolve_layers(12, &damage, Some(&mut shield), Some(&mut plating), &mut hp);
+        assert_eq!(
+            dealt,
+            LayeredDamage {
+                shield: 5,
+                plating: 5,
+                hp: 2
+            }
+        );
+        assert_eq!((shield.hp, plating.hp, hp.hp), (0, 0, 18));
+    }
+
+    #[test]
+    fn shield_bonus_strips_shields_faster() {
+        let damage = damage::Damage {
+            amount: 0,
+            kind: damage::DamageKind::Physical,
+            shield_bonus: 1.,
+        };
+        let mut shield = Shield::new(10, 2., 1.);
+        let mut hp = HealthPoints { hp: 20, max_hp: 20 };
+        let dealt = resolve_layers(8, &damage, Some(&mut shield), None, &mut hp);
+        assert_eq!((dealt.shield, dealt.hp), (10, 3));
+    }
+
+    #[test]
+    fn pierce_skips_plating() {
+        let damage = damage::Damage {
+            amount: 0,
+            kind: damage::DamageKind::Pierce,
+            shield_bonus: 0.,
+        };
+        let mut plating = Plating { hp: 5, max_hp: 5 };
+        let mut hp = HealthPoints { hp: 20, max_hp: 20 };
+        resolve_layers(4, &damage, None, Some(&mut plating), &mut hp);
+        assert_eq!((plating.hp, hp.hp), (5, 16));
+    }
+
+    #[test]
+    fn shield_recharges_after_delay() {
+        let mut shield = Shield::new(10, 2., 2.);
+        shield.hp = 0;
+        shield.since_damage = 0.;
+        shield.tick(secs(1.));
+        shield.tick(secs(1.));
+        assert_eq!(shield.hp, 0);
+        shield.tick(secs(1.));
+        assert_eq!(shield.hp, 2);
+        shield.tick(secs(10.));
+        assert_eq!(shield.hp, 10);
+    }
+
+    #[test]
+    fn dead_entities_do_not_regen() {
+        let mut hp = HealthPoints { hp: 0, max_hp: 20 };
+        let mut regen = Regen::new(RegenAmount::Flat(5.), 1.);
+        regen.tick(secs(1.), &mut hp);
+        assert_eq!(hp.hp, 0);
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::transform::TransformSystem;
+
+use crate::camera;
+use crate::health;
+
+/// number of colours the hp bar steps through from empty (red) to full (green)
+const HP_COLORS: usize = 10;
+const BAR_WIDTH: f32 = 0.8;
+const BAR_HEIGHT: f32 = 0.08;
+
+pub struct HealthBarPlugin;
+impl Plugin for HealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<HealthBar>()
+            .add_startup_system(add_health_bar_assets)
+            .add_system(spawn_health_bars)
+            .add_system(
+                update_health_bars
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// billboard that follows `owner` (kept as a separate entity so it doesn't inherit the owner's rotation)
+#[derive(Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct HealthBar {
+    pub owner: Entity,
+}
+
+impl Default for HealthBar {
+    fn default() -> Self {
+        Self {
+            owner: Entity::PLACEHOLDER,
+        }
+    }
+}
+
+/// the quads a bar is made of (the hp background is left unmarked since it never changes)
+#[derive(Clone, Copy, Component, PartialEq)]
+pub enum BarPart {
+    Hp,
+    ShieldBackground,
+    Shield,
+}
+
+/// one mesh and a small pool of materials shared by every bar
+#[derive(Resource)]
+pub struct HealthBarAssets {
+    pub mesh: Handle<Mesh>,
+    pub background: Handle<StandardMaterial>,
+    pub shield: Handle<StandardMaterial>,
+    pub hp: Vec<Handle<StandardMaterial>>,
+}
+
+impl HealthBarAssets {
+    fn hp_material(&self, fraction: f32) -> Handle<StandardMaterial> {
+        let index = (fraction * (self.hp.len() - 1) as f32).round() as usize;
+        self.hp[index.min(self.hp.len() - 1)].clone()
+    }
+}
+
+fn unlit(color: Color) -> StandardMaterial {
+    StandardMaterial {
+        base_color: color,
+        unlit: true,
+        ..Default::default()
+    }
+}
+
+pub fn add_health_bar_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let hp = (0..HP_COLORS)
+        .map(|i| {
+            let fraction = i as f32 / (HP_COLORS - 1) as f32;
+            materials.add(unlit(Color::rgb(
+                (2. - 2. * fraction).min(1.),
+                (2. * fraction).min(1.),
+                0.,
+            )))
+        })
+        .collect();
+    commands.insert_resource(HealthBarAssets {
+        mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE))),
+        background: materials.add(unlit(Color::rgb(0.05, 0.05, 0.05))),
+        shield: materials.add(unlit(Color::rgb(0.3, 0.8, 1.))),
+        hp,
+    });
+}
+
+fn bar_part(
+    assets: &HealthBarAssets,
+    material: Handle<StandardMaterial>,
+    y: f32,
+    z: f32,
+) -> PbrBundle {
+    PbrBundle {
+        mesh: assets.mesh.clone(),
+        material,
+        transform: Transform::from_xyz(0., y, z).with_scale(Vec3::new(BAR_WIDTH, BAR_HEIGHT, 1.)),
+        ..Default::default()
+    }
+}
+
+pub fn spawn_health_bars(
+    mut commands: Commands,
+    assets: Res<HealthBarAssets>,
+    query: Query<Entity, Added<health::HealthPoints>>,
+) {
+    for owner in query.iter() {
+        commands
+            .spawn((
+                SpatialBundle {
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                HealthBar { owner },
+                Name::new("HealthBar"),
+            ))
+            .with_children(|bar| {
+                bar.spawn(bar_part(&assets, assets.background.clone(), 0., 0.));
+                bar.spawn((
+                    bar_part(&assets, assets.hp_material(1.), 0., 0.001),
+                    BarPart::Hp,
+                ));
+                bar.spawn((
+                    bar_part(&assets, assets.background.clone(), BAR_HEIGHT, 0.),
+                    BarPart::ShieldBackground,
+                ));
+                bar.spawn((
+                    bar_part(&assets, assets.shield.clone(), BAR_HEIGHT, 0.001),
+                    BarPart::Shield,
+                ));
+            });
+    }
+}
+
+/// left-align a fill quad so it covers `fraction` of the bar
+fn set_fill(transform: &mut Transform, fraction: f32) {
+    transform.scale.x = BAR_WIDTH * fraction.max(0.0001);
+    transform.translation.x = -BAR_WIDTH * (1. - fraction) / 2.;
+}
+
+/// follow the owner, face the camera and only show up while the owner is damaged
+#[allow(clippy::type_complexity)]
+pub fn update_health_bars(
+    mut commands: Commands,
+    assets: Res<HealthBarAssets>,
+    camera: Query<&Transform, With<camera::CameraPlayer>>,
+    owners: Query<
+        (
+            &Transform,
+            &health::HealthPoints,
+            Option<&health::Shield>,
+            Option<&Aabb>,
+        ),
+        Without<HealthBar>,
+    >,
+    mut bars: Query<
+        (
+            Entity,
+            &HealthBar,
+            &mut Transform,
+            &mut Visibility,
+            &Children,
+        ),
+        Without<camera::CameraPlayer>,
+    >,
+    mut parts: Query<
+        (
+            &BarPart,
+            &mut Transform,
+            &mut Visibility,
+            &mut Handle<StandardMaterial>,
+        ),
+        (
+            Without<HealthBar>,
+            Without<health::HealthPoints>,
+            Without<camera::CameraPlayer>,
+        ),
+    >,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (entity, bar, mut transform, mut visibility, children) in bars.iter_mut() {
+        let Ok((owner_transform, hp, shield, aabb)) = owners.get(bar.owner) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let hp_fraction = hp.hp as f32 / hp.max_hp.max(1) as f32;
+        let shield_fraction = shield.map(|s| s.hp as f32 / s.max_hp.max(1) as f32);
+        let damaged = hp.hp < hp.max_hp || matches!(shield_fraction, Some(f) if f < 1.);
+        if !damaged || hp.hp == 0 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        let height = aabb.map_or(0.5, |aabb| aabb.half_extents.y) + 0.3;
+        transform.translation = owner_transform.translation + Vec3::Y * height;
+        transform.rotation = camera.rotation;
+        for &child in children.iter() {
+            let Ok((part, mut part_transform, mut part_visibility, mut material)) =
+                parts.get_mut(child)
+            else {
+                continue;
+            };
+            match (part, shield_fraction) {
+                (BarPart::Hp, _) => {
+                    set_fill(&mut part_transform, hp_fraction);
+                    *material = assets.hp_material(hp_fraction);
+                }
+                (BarPart::ShieldBackground | BarPart::Shield, None) => {
+                    *part_visibility = Visibility::Hidden;
+                }
+                (BarPart::ShieldBackground, Some(_)) => {
+                    *part_visibility = Visibility::Inherited;
+                }
+                (BarPart::Shield, Some(fraction)) => {
+                    *part_visibility = Visibility::Inherited;
+                    set_fill(&mut part_transform, fraction);
+                }
+            }
+        }
+    }
+}
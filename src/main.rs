@@ -5,14 +5,32 @@ use bevy::prelude::*;
 use bevy_mod_picking::*;
 
 mod assets;
+mod aura;
+mod boss;
 mod camera;
+mod damage;
+mod damage_text;
+mod death;
+mod economy;
 mod enemy;
+mod flow_field;
+mod goal;
 mod grid;
 mod health;
+mod health_bar;
 mod map;
 mod orb;
+mod pickup;
+mod placement;
 mod quad_tree;
+mod status;
+mod targeting;
 mod tower;
+mod turret;
+mod upgrade;
+mod veterancy;
+mod wall;
+mod wave;
 
 fn main() {
     App::new()
@@ -41,7 +59,6 @@ fn main() {
                 map::spawn_basic_scene,
                 grid::spawn_grid,
                 apply_system_buffers,
-                tower::spawn_default_towers,
                 quad_tree::visualize_quad_tree_leaves,
             ).chain(),
         )
@@ -56,10 +73,27 @@ fn main() {
 
         // my plugins
         .add_plugin(camera::CameraPlayerPlugin)
+        .add_plugin(aura::AuraPlugin)
+        .add_plugin(damage::DamagePlugin)
+        .add_plugin(damage_text::DamageTextPlugin)
+        .add_plugin(economy::EconomyPlugin)
+        .add_plugin(goal::GoalPlugin)
         .add_plugin(health::HealthPointsPlugin)
+        .add_plugin(health_bar::HealthBarPlugin)
         .add_plugin(enemy::EnemyPlugin)
+        .add_plugin(flow_field::FlowFieldPlugin)
+        .add_plugin(boss::BossPlugin)
         .add_plugin(tower::TowerPlugin)
+        .add_plugin(upgrade::UpgradePlugin)
+        .add_plugin(veterancy::VeterancyPlugin)
         .add_plugin(orb::OrbPlugin)
+        .add_plugin(pickup::PickupPlugin)
+        .add_plugin(placement::PlacementPlugin)
+        .add_plugin(status::StatusPlugin)
+        .add_plugin(targeting::TargetingPlugin)
+        .add_plugin(turret::TurretPlugin)
+        .add_plugin(wall::WallPlugin)
+        .add_plugin(wave::WavePlugin)
 
         // core systems
         .add_systems(
@@ -67,7 +101,11 @@ fn main() {
                 enemy::index_enemies,
                 orb::index_orbs,
                 quad_tree::update_leaf_node_color,
+                status::tick_status_effects,
                 enemy::take_damage,
+                health::apply_damage,
+                death::trigger_on_death,
+                health::despawn_dead,
                 orb::despawn_reach_ground,
                 quad_tree::clear_quad_tree,
             ).chain()
@@ -1,6 +1,17 @@
 use bevy::prelude::*;
 
-pub fn spawn_basic_scene(mut commands: Commands) {
+/// where waves enter the map (referenced by index from the wave definitions)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct SpawnPoint {
+    pub id: usize,
+}
+
+pub fn spawn_basic_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
     commands
         .spawn(PointLightBundle {
             point_light: PointLight {
@@ -12,4 +23,32 @@ pub fn spawn_basic_scene(mut commands: Commands) {
             ..Default::default()
         })
         .insert(Name::new("Light"));
+
+    // spawn points sit just off the corners of the grid
+    let mesh = meshes.add(Mesh::from(shape::Box::new(1.5, 0.05, 1.5)));
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.6, 0.1, 0.6, 0.5),
+        alpha_mode: AlphaMode::Blend,
+        ..Default::default()
+    });
+    for (id, location) in [
+        Vec3::new(-13.5, 0., -15.),
+        Vec3::new(13.5, 0., 15.),
+        Vec3::new(-13.5, 0., 15.),
+        Vec3::new(13.5, 0., -15.),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(location),
+                ..Default::default()
+            },
+            SpawnPoint { id },
+            Name::new("SpawnPoint"),
+        ));
+    }
 }
@@ -5,7 +5,9 @@ use crate::quad_tree;
 pub struct OrbPlugin;
 impl Plugin for OrbPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Orb>().add_system(move_orb);
+        app.register_type::<Orb>()
+            .add_system(move_orb)
+            .add_system(despawn_expired);
     }
 }
 
@@ -15,6 +17,10 @@ pub struct Orb {
     pub direction: Vec3,
     pub target: Vec3,
     pub speed: f32,
+    /// orbs fired upwards never reach the ground so they also expire
+    pub lifetime: Timer,
+    /// the tower that fired the orb
+    pub source: Option<Entity>,
 }
 
 pub fn move_orb(time: Res<Time>, mut query: Query<(&mut Transform, &Orb)>) {
@@ -57,3 +63,14 @@ pub fn despawn_reach_ground(mut commands: Commands, query: Query<(Entity, &Trans
         }
     }
 }
+
+pub fn despawn_expired(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Orb)>) {
+    for (entity, mut orb) in query.iter_mut() {
+        orb.lifetime.tick(time.delta());
+        if orb.lifetime.finished() {
+            if let Some(ec) = commands.get_entity(entity) {
+                ec.despawn_recursive();
+            }
+        }
+    }
+}
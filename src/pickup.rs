@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::camera;
+
+pub struct PickupPlugin;
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Pickup>()
+            .add_event::<PickupCollected>()
+            .add_startup_system(add_pickup_assets)
+            .add_system(collect_pickups)
+            .add_system(expire_pickups)
+            .add_system(log_pickups);
+    }
+}
+
+/// something left on the ground that the camera cursor can collect
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Pickup {
+    pub gold: usize,
+    pub lifetime: Timer,
+}
+
+pub struct PickupCollected {
+    pub gold: usize,
+}
+
+/// shared mesh and material for every pickup
+#[derive(Resource)]
+pub struct PickupAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+pub fn add_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(PickupAssets {
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 0.15,
+            ..Default::default()
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1., 0.8, 0.),
+            emissive: Color::rgb(0.5, 0.4, 0.),
+            ..Default::default()
+        }),
+    });
+}
+
+pub fn spawn_pickup(commands: &mut Commands, assets: &PickupAssets, location: Vec3, gold: usize) {
+    commands.spawn((
+        PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.material.clone(),
+            transform: Transform::from_translation(location + Vec3::new(0., 0.25, 0.)),
+            ..Default::default()
+        },
+        Pickup {
+            gold,
+            lifetime: Timer::from_seconds(15., TimerMode::Once),
+        },
+        Name::new("Pickup"),
+    ));
+}
+
+/// pickups are collected by moving the camera cursor over them
+pub fn collect_pickups(
+    mut commands: Commands,
+    camera: Query<&camera::CameraLookAt>,
+    pickups: Query<(Entity, &Transform, &Pickup)>,
+    mut collected_events: EventWriter<PickupCollected>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let cursor = Vec2::new(camera.target.x, camera.target.z);
+    for (entity, transform, pickup) in pickups.iter() {
+        let position = Vec2::new(transform.translation.x, transform.translation.z);
+        if (position - cursor).length() < 1.0 {
+            collected_events.send(PickupCollected { gold: pickup.gold });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn expire_pickups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pickups: Query<(Entity, &mut Pickup)>,
+) {
+    for (entity, mut pickup) in pickups.iter_mut() {
+        pickup.lifetime.tick(time.delta());
+        if pickup.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn log_pickups(mut collected_events: EventReader<PickupCollected>) {
+    for ev in collected_events.iter() {
+        info!("picked up {} gold", ev.gold);
+    }
+}
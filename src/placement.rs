@@ -0,0 +1,445 @@
+use bevy::prelude::*;
+use bevy_mod_picking::{Hover, Selection};
+use std::collections::HashMap;
+
+use crate::assets;
+use crate::economy;
+use crate::grid;
+use crate::tower;
+
+pub struct PlacementPlugin;
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovingTower>()
+            .init_resource::<GhostMeshes>()
+            .add_event::<TowerSold>()
+            .add_startup_system(spawn_placement_preview)
+            .add_system(sell_selected_tower)
+            .add_system(move_tower)
+            .add_system(
+                update_placement_preview
+                    .after(sell_selected_tower)
+                    .after(move_tower),
+            )
+            .add_system(log_sales);
+    }
+}
+
+/// ground ring showing the range of the hovered or selected tower (or of the tower being placed)
+#[derive(Component)]
+pub struct RangeRing;
+
+/// see-through tower on the hovered cell (green where it can be placed, red where it can't)
+#[derive(Component)]
+pub struct GhostTower;
+
+#[derive(Resource)]
+pub struct PlacementMaterials {
+    pub valid: Handle<StandardMaterial>,
+    pub invalid: Handle<StandardMaterial>,
+}
+
+/// tower meshes the ghost has shown, by path (so each is only loaded once)
+#[derive(Resource, Default)]
+pub struct GhostMeshes {
+    pub meshes: HashMap<String, Handle<Mesh>>,
+}
+
+/// sent when a tower is sold (`refund` is the gold given back)
+pub struct TowerSold {
+    pub tower: Entity,
+    pub id: String,
+    pub refund: usize,
+}
+
+/// tower picked up with M, waiting for a free cell to be selected
+#[derive(Resource, Default)]
+pub struct MovingTower {
+    pub tower: Option<Entity>,
+}
+
+/// the share of the gold spent on a tower (and its upgrades) given back when it's sold
+pub fn refund(tower: &tower::Tower, definitions: &tower::TowerDefinitions) -> usize {
+    (tower.invested as f32 * definitions.sell_refund.clamp(0., 1.)).round() as usize
+}
+
+/// Delete (or Backspace) sells the selected tower and frees its cell
+#[allow(clippy::too_many_arguments)]
+pub fn sell_selected_tower(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<tower::TowerDefinitions>>,
+    towers: Query<(Entity, &tower::Tower, &Selection)>,
+    mut cells: Query<&mut grid::Cell>,
+    mut moving: ResMut<MovingTower>,
+    mut sold_events: EventWriter<TowerSold>,
+) {
+    if !keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        return;
+    }
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    for (entity, tower, selection) in towers.iter() {
+        if !selection.selected() {
+            continue;
+        }
+        if let Ok(mut cell) = cells.get_mut(tower.cell) {
+            cell.is_occupied = false;
+        }
+        if moving.tower == Some(entity) {
+            moving.tower = None;
+        }
+        sold_events.send(TowerSold {
+            tower: entity,
+            id: tower.id.clone(),
+            refund: refund(tower, definitions),
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// M picks up the selected tower, then M again drops it on the selected cell if it is free
+/// (Escape puts it back down where it was)
+pub fn move_tower(
+    keys: Res<Input<KeyCode>>,
+    mut moving: ResMut<MovingTower>,
+    mut towers: Query<(Entity, &mut tower::Tower, &mut Transform, &Selection)>,
+    mut cells: Query<(Entity, &mut grid::Cell, &Transform, &Selection), Without<tower::Tower>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) && moving.tower.is_some() {
+        moving.tower = None;
+        info!("cancelled moving tower");
+        return;
+    }
+    if !keys.just_pressed(KeyCode::M) {
+        return;
+    }
+    let Some(moving_tower) = moving.tower else {
+        moving.tower = towers
+            .iter()
+            .find(|(_, _, _, selection)| selection.selected())
+            .map(|(entity, _, _, _)| entity);
+        if moving.tower.is_some() {
+            info!("select a free cell and press M again to move the tower");
+        }
+        return;
+    };
+    let Some((cell_entity, cell, cell_transform, _)) = cells
+        .iter()
+        .find(|(_, _, _, selection)| selection.selected())
+    else {
+        return;
+    };
+    if cell.is_occupied {
+        info!("there is already a tower on this cell");
+        return;
+    }
+    let destination = cell_transform.translation;
+    let Ok((_, mut tower, mut transform, _)) = towers.get_mut(moving_tower) else {
+        moving.tower = None;
+        return;
+    };
+    if let Ok((_, mut old_cell, _, _)) = cells.get_mut(tower.cell) {
+        old_cell.is_occupied = false;
+    }
+    if let Ok((_, mut new_cell, _, _)) = cells.get_mut(cell_entity) {
+        new_cell.is_occupied = true;
+    }
+    tower.cell = cell_entity;
+    transform.translation = destination + Vec3::new(0., 0.1, 0.);
+    moving.tower = None;
+}
+
+pub fn spawn_placement_preview(
+    mut commands: Commands,
+    game_assets: Res<assets::GameAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // a unit ring that is scaled to the range (the tube is thin enough to stay thin when stretched)
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Torus {
+                radius: 1.,
+                ring_radius: 0.008,
+                subdivisions_segments: 64,
+                subdivisions_sides: 8,
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1., 1., 1., 0.6),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            }),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        RangeRing,
+        Name::new("RangeRing"),
+    ));
+    let ghost_material = |color: Color| StandardMaterial {
+        base_color: color,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..Default::default()
+    };
+    let placement_materials = PlacementMaterials {
+        valid: materials.add(ghost_material(Color::rgba(0.1, 0.9, 0.2, 0.4))),
+        invalid: materials.add(ghost_material(Color::rgba(0.9, 0.1, 0.1, 0.4))),
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: game_assets.tower_mesh.clone(),
+            material: placement_materials.valid.clone(),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        GhostTower,
+        Name::new("GhostTower"),
+    ));
+    commands.insert_resource(placement_materials);
+}
+
+/// places the range ring and the ghost tower from the current hover and selection
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_placement_preview(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<tower::TowerDefinitions>>,
+    selected_type: Res<tower::SelectedTowerType>,
+    wallet: Res<economy::Wallet>,
+    moving: Res<MovingTower>,
+    placement_materials: Res<PlacementMaterials>,
+    mut ghost_meshes: ResMut<GhostMeshes>,
+    towers: Query<(Entity, &Transform, &tower::Range, &Hover, &Selection), With<tower::Tower>>,
+    cells: Query<(&Transform, &grid::Cell, &Hover)>,
+    mut ring: Query<
+        (&mut Transform, &mut Visibility),
+        (
+            With<RangeRing>,
+            Without<GhostTower>,
+            Without<tower::Tower>,
+            Without<grid::Cell>,
+        ),
+    >,
+    mut ghost: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut Handle<Mesh>,
+            &mut Handle<StandardMaterial>,
+        ),
+        (
+            With<GhostTower>,
+            Without<RangeRing>,
+            Without<tower::Tower>,
+            Without<grid::Cell>,
+        ),
+    >,
+) {
+    let (
+        Ok((mut ring_transform, mut ring_visibility)),
+        Ok((mut ghost_transform, mut ghost_visibility, mut ghost_mesh, mut ghost_material)),
+    ) = (ring.get_single_mut(), ghost.get_single_mut())
+    else {
+        return;
+    };
+    let definition = definitions
+        .get(&game_assets.towers)
+        .and_then(|definitions| definitions.get(&selected_type.id));
+    let moving_tower = moving.tower.and_then(|entity| towers.get(entity).ok());
+
+    // ghost on the hovered cell, either for the tower being moved or the tower type `T` places
+    let hovered_cell = cells.iter().find(|(_, _, hover)| hover.hovered());
+    let ghost_range = match (hovered_cell, moving_tower, definition) {
+        (Some((cell_transform, cell, _)), Some((_, _, range, _, _)), _) => {
+            *ghost_material = if cell.is_occupied {
+                placement_materials.invalid.clone()
+            } else {
+                placement_materials.valid.clone()
+            };
+            ghost_transform.translation = cell_transform.translation + Vec3::new(0., 0.1, 0.);
+            Some(range.range)
+        }
+        (Some((cell_transform, cell, _)), None, Some(definition)) => {
+            *ghost_material = if cell.is_occupied || wallet.gold < definition.cost {
+                placement_materials.invalid.clone()
+            } else {
+                placement_materials.valid.clone()
+            };
+            let mesh = match &definition.mesh {
+                Some(path) => ghost_meshes
+                    .meshes
+                    .entry(path.clone())
+                    .or_insert_with(|| asset_server.load(path.as_str()))
+                    .clone(),
+                None => game_assets.tower_mesh.clone(),
+            };
+            if *ghost_mesh != mesh {
+                *ghost_mesh = mesh;
+            }
+            ghost_transform.translation = cell_transform.translation + Vec3::new(0., 0.1, 0.);
+            Some(definition.range)
+        }
+        _ => None,
+    };
+    *ghost_visibility = match ghost_range {
+        Some(_) => Visibility::Inherited,
+        None => Visibility::Hidden,
+    };
+
+    // the ring follows the hovered tower, then the selected one, then the ghost
+    let shown_tower = towers
+        .iter()
+        .find(|(_, _, _, hover, _)| hover.hovered())
+        .or_else(|| {
+            towers
+                .iter()
+                .find(|(_, _, _, _, selection)| selection.selected())
+        });
+    let ring_at = match (shown_tower, ghost_range) {
+        (Some((_, transform, range, _, _)), _) => Some((transform.translation, range.range)),
+        (None, Some(range)) => Some((ghost_transform.translation, range)),
+        (None, None) => None,
+    };
+    match ring_at {
+        Some((position, range)) => {
+            ring_transform.translation = Vec3::new(position.x, 0.05, position.z);
+            ring_transform.scale = Vec3::new(range, 1., range);
+            *ring_visibility = Visibility::Inherited;
+        }
+        None => *ring_visibility = Visibility::Hidden,
+    }
+}
+
+pub fn log_sales(mut sold_events: EventReader<TowerSold>) {
+    for ev in sold_events.iter() {
+        info!("sold {} tower {:?} for {} gold", ev.id, ev.tower, ev.refund);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upgrade;
+
+    const DEFINITIONS: &str = r#"(
+        towers: [(
+            id: "basic",
+            name: "Basic",
+            color: (0.0, 0.7, 0.7),
+            cost: 60,
+            range: 10.0,
+            fire_rate: 1.0,
+            damage: 5,
+            upgrades: [(
+                name: "Long Barrel",
+                cost: 40,
+                modifiers: [Range(4.0)],
+                next: [(name: "Sniper", cost: 100, modifiers: [Damage(10)])],
+            )],
+        )],
+    )"#;
+
+    /// holds `key` down for a single frame
+    fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        *app.world.resource_mut::<Input<KeyCode>>() = Input::default();
+    }
+
+    fn select(app: &mut App, entity: Entity, selected: bool) {
+        let mut selection = app.world.get_mut::<Selection>(entity).unwrap();
+        selection.set_selected(selected);
+    }
+
+    /// sells `tower` and returns the refund in the `TowerSold` event
+    fn sell(app: &mut App, tower: Entity) -> usize {
+        select(app, tower, true);
+        press(app, KeyCode::Delete);
+        let events = app.world.resource::<Events<TowerSold>>();
+        let mut reader = events.get_reader();
+        let sold: Vec<_> = reader.iter(events).filter(|ev| ev.tower == tower).collect();
+        assert_eq!(sold.len(), 1);
+        sold[0].refund
+    }
+
+    #[test]
+    fn only_gold_actually_spent_is_refunded() {
+        let mut app = App::new();
+        app.add_plugin(TaskPoolPlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<tower::TowerDefinitions>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<economy::Wallet>()
+            .init_resource::<MovingTower>()
+            .insert_resource(tower::SelectedTowerType {
+                id: "basic".to_string(),
+            })
+            .add_event::<economy::GoldSpent>()
+            .add_event::<upgrade::TowerUpgraded>()
+            .add_event::<TowerSold>()
+            .add_system(tower::spawn_default_towers)
+            .add_system(tower::spawn_tower)
+            .add_system(upgrade::upgrade_selected_tower)
+            .add_system(sell_selected_tower);
+        let towers = app
+            .world
+            .resource_mut::<Assets<tower::TowerDefinitions>>()
+            .add(ron::from_str(DEFINITIONS).unwrap());
+        app.insert_resource(assets::GameAssets {
+            hexagon_mesh: Handle::default(),
+            tower_mesh: Handle::default(),
+            wall_mesh: Handle::default(),
+            waves: Handle::default(),
+            enemies: Handle::default(),
+            towers,
+            font: Handle::default(),
+        });
+        // a free default tower goes up next to the origin, the other cell is left to buy on
+        app.world
+            .spawn((Transform::default(), grid::Cell::default()));
+        let shop_cell = app
+            .world
+            .spawn((
+                Transform::from_xyz(20., 0., 20.),
+                Selection::default(),
+                grid::Cell::default(),
+            ))
+            .id();
+        app.update();
+        select(&mut app, shop_cell, true);
+        press(&mut app, KeyCode::T);
+        select(&mut app, shop_cell, false);
+
+        let mut query = app.world.query::<(Entity, &tower::Tower)>();
+        let towers: Vec<_> = query
+            .iter(&app.world)
+            .map(|(entity, tower)| (entity, tower.cell))
+            .collect();
+        assert_eq!(towers.len(), 2);
+        let bought = towers
+            .iter()
+            .find(|(_, cell)| *cell == shop_cell)
+            .unwrap()
+            .0;
+        let default = towers
+            .iter()
+            .find(|(_, cell)| *cell != shop_cell)
+            .unwrap()
+            .0;
+
+        // bought for 60, then upgraded for 40 and 100
+        select(&mut app, bought, true);
+        press(&mut app, KeyCode::U);
+        press(&mut app, KeyCode::U);
+        let spent = economy::Wallet::default().gold - app.world.resource::<economy::Wallet>().gold;
+        assert_eq!(spent, 200);
+        assert_eq!(sell(&mut app, bought), 150);
+        assert_eq!(sell(&mut app, default), 0);
+    }
+}
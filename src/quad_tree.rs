@@ -4,6 +4,7 @@ use std::collections::{HashSet, VecDeque};
 #[derive(Clone, Debug, Resource)]
 pub struct QuadTree {
     pub bounds: [Vec2; 2],
+    #[allow(dead_code)]
     pub min_size: f32,
     pub children: Option<[Box<QuadTree>; 4]>,
     pub objects: Option<HashSet<Entity>>,
@@ -77,6 +78,7 @@ impl QuadTree {
         leaf_nodes
     }
 
+    #[allow(clippy::never_loop)]
     pub fn insert(&mut self, entity: Entity, position: Vec2) -> Result<(), ()> {
         while let Some(children) = &mut self.children {
             for child in children {
@@ -164,7 +166,7 @@ pub fn update_leaf_node_color(
     mut materials: ResMut<Assets<StandardMaterial>>,
     leaf_node_query: Query<(&LeafNode, &Handle<StandardMaterial>)>,
 ) {
-    let leaf_node_vec: Vec<_> = quad_tree.get_leaf_nodes().into();
+    let leaf_node_vec: Vec<_> = quad_tree.get_leaf_nodes();
     for (leaf_node, material_handle) in leaf_node_query.iter() {
         let material = materials.get_mut(material_handle).unwrap();
         let orig_color = Color::rgba(1., 0., 0., 0.04);
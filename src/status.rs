@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::damage;
+use crate::health;
+
+pub struct StatusPlugin;
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StatusEffects>()
+            .register_type::<OnHit>();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, FromReflect, PartialEq, Reflect)]
+pub enum StatusKind {
+    /// removes `magnitude` (0 to 1) of the target's speed per stack
+    #[default]
+    Slow,
+    /// `magnitude` fire damage per second per stack
+    Burn,
+    /// `magnitude` damage per second per stack (ignores armor)
+    Poison,
+    /// no movement at all
+    Stun,
+}
+
+/// what happens when the same kind of effect is applied again
+#[derive(Clone, Copy, Debug, Default, Deserialize, FromReflect, PartialEq, Reflect)]
+pub enum Stacking {
+    /// keep a single stack and restart the duration (the stronger magnitude wins)
+    #[default]
+    Refresh,
+    /// add a stack and restart the duration
+    Stack,
+    /// add a stack (up to the limit) and restart the duration
+    MaxStacks(usize),
+}
+
+#[derive(Clone, Debug, Default, Deserialize, FromReflect, Reflect)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    #[serde(default)]
+    pub magnitude: f32,
+    /// seconds
+    pub duration: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+}
+
+#[derive(Clone, Debug, Default, FromReflect, Reflect)]
+pub struct ActiveEffect {
+    pub effect: StatusEffect,
+    pub stacks: usize,
+    pub timer: Timer,
+    /// damage over time that hasn't added up to a whole hp yet
+    pub accumulated: f32,
+    /// whoever applied the effect last (credited with the damage over time)
+    pub source: Option<Entity>,
+    /// kept apart from the same kind of effect from orbs
+    pub from_aura: bool,
+}
+
+/// timed effects currently on an entity
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct StatusEffects {
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl StatusEffects {
+    /// an effect from an orb (all towers share the stacks of each kind, and the stack limit)
+    pub fn apply(&mut self, effect: &StatusEffect, source: Option<Entity>) {
+        let existing = self
+            .effects
+            .iter_mut()
+            .find(|a| !a.from_aura && a.effect.kind == effect.kind);
+        let Some(active) = existing else {
+            self.effects.push(ActiveEffect::new(effect, source, false));
+            return;
+        };
+        match effect.stacking {
+            Stacking::Refresh => {
+                active.effect.magnitude = active.effect.magnitude.max(effect.magnitude);
+            }
+            Stacking::Stack => {
+                active.stacks += 1;
+            }
+            Stacking::MaxStacks(max_stacks) => {
+                active.stacks = (active.stacks + 1).min(max_stacks);
+            }
+        }
+        active.timer = Timer::from_seconds(effect.duration, TimerMode::Once);
+        active.source = source;
+    }
+
+    /// an effect from an aura: like tower buffs, auras don't add up and the strongest of each
+    /// kind wins (a single stack, on top of any orb effect of that kind)
+    pub fn apply_aura(&mut self, effect: &StatusEffect, source: Entity) {
+        let existing = self
+            .effects
+            .iter_mut()
+            .find(|a| a.from_aura && a.effect.kind == effect.kind);
+        match existing {
+            None => self.effects.push(ActiveEffect::new(effect, Some(source), true)),
+            Some(active) if effect.magnitude >= active.effect.magnitude => {
+                *active = ActiveEffect {
+                    accumulated: active.accumulated,
+                    ..ActiveEffect::new(effect, Some(source), true)
+                };
+            }
+            // a weaker aura takes over once the stronger one wears off
+            Some(_) => {}
+        }
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        let mut multiplier = 1.;
+        for active in self.effects.iter() {
+            match active.effect.kind {
+                StatusKind::Slow => {
+                    multiplier *= (1. - active.effect.magnitude * active.stacks as f32).max(0.1);
+                }
+                StatusKind::Stun => multiplier = 0.,
+                StatusKind::Burn | StatusKind::Poison => {}
+            }
+        }
+        multiplier
+    }
+
+    /// counts the effects down (dropping the ones that ran out) and returns the whole points of
+    /// damage over time that are due, with whoever is credited for them
+    pub fn tick(&mut self, delta: Duration) -> Vec<(Option<Entity>, damage::Damage)> {
+        let mut due = vec![];
+        for active in self.effects.iter_mut() {
+            active.timer.tick(delta);
+            let kind = match active.effect.kind {
+                StatusKind::Burn => damage::DamageKind::Fire,
+                StatusKind::Poison => damage::DamageKind::Pierce,
+                StatusKind::Slow | StatusKind::Stun => continue,
+            };
+            active.accumulated +=
+                active.effect.magnitude * active.stacks as f32 * delta.as_secs_f32();
+            if active.accumulated < 1. {
+                continue;
+            }
+            let amount = active.accumulated.floor();
+            active.accumulated -= amount;
+            due.push((
+                active.source,
+                damage::Damage {
+                    amount: amount as usize,
+                    kind,
+                    ..Default::default()
+                },
+            ));
+        }
+        self.effects.retain(|active| !active.timer.finished());
+        due
+    }
+}
+
+impl ActiveEffect {
+    fn new(effect: &StatusEffect, source: Option<Entity>, from_aura: bool) -> Self {
+        Self {
+            effect: effect.clone(),
+            stacks: 1,
+            timer: Timer::from_seconds(effect.duration, TimerMode::Once),
+            accumulated: 0.,
+            source,
+            from_aura,
+        }
+    }
+}
+
+/// status effects applied by every orb a tower fires
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct OnHit {
+    pub effects: Vec<StatusEffect>,
+}
+
+/// count down effects and deal damage over time
+pub fn tick_status_effects(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut StatusEffects)>,
+    mut hits: EventWriter<health::Hit>,
+) {
+    for (entity, mut status) in query.iter_mut() {
+        if status.effects.is_empty() {
+            continue;
+        }
+        for (source, damage) in status.tick(time.delta()) {
+            hits.send(health::Hit {
+                source,
+                target: entity,
+                damage,
+                critical: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slow(magnitude: f32, stacking: Stacking) -> StatusEffect {
+        StatusEffect {
+            kind: StatusKind::Slow,
+            magnitude,
+            duration: 1.,
+            stacking,
+        }
+    }
+
+    fn speed_is(status: &StatusEffects, multiplier: f32) -> bool {
+        (status.speed_multiplier() - multiplier).abs() < 1e-5
+    }
+
+    #[test]
+    fn refresh_keeps_the_strongest_magnitude() {
+        let mut status = StatusEffects::default();
+        status.apply(&slow(0.4, Stacking::Refresh), None);
+        status.effects[0].timer.tick(Duration::from_secs_f32(0.5));
+        status.apply(&slow(0.2, Stacking::Refresh), None);
+        assert_eq!(status.effects.len(), 1);
+        assert_eq!(status.effects[0].stacks, 1);
+        assert!(speed_is(&status, 0.6));
+        assert_eq!(status.effects[0].timer.elapsed_secs(), 0.);
+    }
+
+    #[test]
+    fn stacks_are_shared_between_towers() {
+        let (a, b) = (Some(Entity::from_raw(1)), Some(Entity::from_raw(2)));
+        let mut status = StatusEffects::default();
+        for source in [a, b, a] {
+            status.apply(&slow(0.1, Stacking::Stack), source);
+        }
+        assert_eq!(status.effects.len(), 1);
+        assert!(speed_is(&status, 0.7));
+        // the limit holds however many towers pile on
+        let mut status = StatusEffects::default();
+        for source in [a, b, a, b, a] {
+            status.apply(&slow(0.2, Stacking::MaxStacks(3)), source);
+        }
+        assert_eq!(status.effects[0].stacks, 3);
+        assert!(speed_is(&status, 0.4));
+        assert_eq!(status.effects[0].source, a);
+    }
+
+    #[test]
+    fn stun_stops_movement() {
+        let mut status = StatusEffects::default();
+        status.apply(&slow(0.5, Stacking::Refresh), None);
+        status.apply(
+            &StatusEffect {
+                kind: StatusKind::Stun,
+                duration: 0.5,
+                ..Default::default()
+            },
+            None,
+        );
+        assert_eq!(status.speed_multiplier(), 0.);
+        status.tick(Duration::from_secs_f32(0.6));
+        assert!(speed_is(&status, 0.5));
+    }
+
+    #[test]
+    fn burn_and_poison_deal_damage_over_time() {
+        let tower = Some(Entity::from_raw(1));
+        let mut status = StatusEffects::default();
+        let burn = StatusEffect {
+            kind: StatusKind::Burn,
+            magnitude: 1.5,
+            duration: 2.,
+            stacking: Stacking::Refresh,
+        };
+        let poison = StatusEffect {
+            kind: StatusKind::Poison,
+            magnitude: 1.,
+            duration: 10.,
+            stacking: Stacking::Stack,
+        };
+        status.apply(&burn, tower);
+        status.apply(&poison, tower);
+        status.apply(&poison, tower);
+
+        // half a second: 0.75 burn isn't a whole point yet, two stacks of poison are
+        let due = status.tick(Duration::from_secs_f32(0.5));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, tower);
+        assert_eq!(due[0].1.amount, 1);
+        assert_eq!(due[0].1.kind, damage::DamageKind::Pierce);
+
+        // the burn's leftover carries over, then it runs out and is dropped
+        let due = status.tick(Duration::from_secs_f32(1.5));
+        let burnt: usize = due
+            .iter()
+            .filter(|(_, damage)| damage.kind == damage::DamageKind::Fire)
+            .map(|(_, damage)| damage.amount)
+            .sum();
+        assert_eq!(burnt, 3);
+        assert_eq!(status.effects.len(), 1);
+    }
+
+    #[test]
+    fn strongest_aura_wins() {
+        let (weak, strong) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut status = StatusEffects::default();
+        for _ in 0..3 {
+            status.apply_aura(&slow(0.2, Stacking::Refresh), weak);
+            status.apply_aura(&slow(0.3, Stacking::Stack), strong);
+        }
+        assert_eq!(status.effects.len(), 1);
+        assert_eq!(status.effects[0].stacks, 1);
+        assert_eq!(status.effects[0].source, Some(strong));
+        assert!(speed_is(&status, 0.7));
+    }
+
+    #[test]
+    fn auras_leave_orb_effects_alone() {
+        let frost = slow(0.2, Stacking::MaxStacks(3));
+        let tower = Some(Entity::from_raw(1));
+        let mut status = StatusEffects::default();
+        for _ in 0..4 {
+            status.apply(&frost, tower);
+        }
+        status.effects[0].timer.tick(Duration::from_secs_f32(0.5));
+        status.apply_aura(&slow(0.3, Stacking::Refresh), Entity::from_raw(2));
+        status.apply_aura(&slow(0.3, Stacking::Refresh), Entity::from_raw(2));
+
+        // three stacks of frost (60%) and the aura (30%), not three of the aura's 30%
+        assert_eq!(status.effects.len(), 2);
+        assert!(speed_is(&status, 0.4 * 0.7));
+        // the aura's pulses don't restart the frost
+        assert!((status.effects[0].timer.remaining_secs() - 0.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,413 @@
+use bevy::prelude::*;
+use bevy_mod_picking::Selection;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::aura;
+use crate::enemy;
+use crate::flow_field;
+use crate::goal;
+use crate::health;
+use crate::status;
+use crate::tower;
+
+pub struct TargetingPlugin;
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TargetingMode>()
+            .register_type::<RetargetRule>()
+            .register_type::<CurrentTarget>()
+            .register_type::<Accuracy>()
+            .add_system(cycle_targeting_mode)
+            .add_system(
+                acquire_targets
+                    .before(tower::tower_shoot)
+                    .run_if(in_state(goal::GameState::Playing)),
+            );
+    }
+}
+
+/// which enemy in range a tower prefers
+#[derive(Clone, Copy, Component, Debug, Default, Deserialize, PartialEq, Reflect)]
+#[reflect(Component)]
+pub enum TargetingMode {
+    #[default]
+    Closest,
+    /// furthest along the path (nearest the goal)
+    First,
+    /// least far along the path
+    Last,
+    /// highest max hp
+    Strongest,
+    /// lowest current hp
+    Weakest,
+    /// highest current hp
+    MostHp,
+    Fastest,
+    Random,
+}
+
+impl TargetingMode {
+    pub const ALL: [TargetingMode; 8] = [
+        TargetingMode::Closest,
+        TargetingMode::First,
+        TargetingMode::Last,
+        TargetingMode::Strongest,
+        TargetingMode::Weakest,
+        TargetingMode::MostHp,
+        TargetingMode::Fastest,
+        TargetingMode::Random,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// lower is better
+    fn score(self, candidate: &TargetCandidate) -> f32 {
+        match self {
+            TargetingMode::Closest => candidate.distance,
+            TargetingMode::First => candidate.goal_distance,
+            TargetingMode::Last => -candidate.goal_distance,
+            TargetingMode::Strongest => -(candidate.max_hp as f32),
+            TargetingMode::Weakest => candidate.hp as f32,
+            TargetingMode::MostHp => -(candidate.hp as f32),
+            TargetingMode::Fastest => -candidate.speed,
+            TargetingMode::Random => 0.,
+        }
+    }
+
+    pub fn pick<'a>(
+        self,
+        candidates: &'a [TargetCandidate],
+        rng: &mut impl Rng,
+    ) -> Option<&'a TargetCandidate> {
+        if self == TargetingMode::Random {
+            return candidates.choose(rng);
+        }
+        candidates
+            .iter()
+            .min_by(|a, b| self.score(a).total_cmp(&self.score(b)))
+    }
+}
+
+/// when a tower gives up a target that is still alive and in range
+#[derive(Clone, Copy, Component, Debug, Default, Deserialize, PartialEq, Reflect)]
+#[reflect(Component)]
+pub enum RetargetRule {
+    /// never (keep shooting until the target dies or leaves range)
+    #[default]
+    Lock,
+    /// whenever the targeting mode prefers another enemy
+    Always,
+    /// once the target has been held for this many seconds and the mode prefers another enemy
+    After(f32),
+}
+
+/// the enemy a tower is locked on to (kept up to date every frame so it can be used for aiming)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct CurrentTarget {
+    pub entity: Option<Entity>,
+    /// seconds the current target has been held for
+    pub held: f32,
+}
+
+impl CurrentTarget {
+    /// keep the current target if it is still a candidate and the rule allows it, otherwise pick a new one
+    pub fn update(
+        &mut self,
+        mode: TargetingMode,
+        rule: RetargetRule,
+        candidates: &[TargetCandidate],
+        delta: f32,
+        rng: &mut impl Rng,
+    ) {
+        self.held += delta;
+        let still_valid = match self.entity {
+            Some(e) => candidates.iter().any(|c| c.entity == e),
+            None => false,
+        };
+        let keep = still_valid
+            && match rule {
+                RetargetRule::Lock => true,
+                RetargetRule::Always => false,
+                RetargetRule::After(seconds) => self.held < seconds,
+            };
+        if keep {
+            return;
+        }
+        let picked = mode.pick(candidates, rng).map(|c| c.entity);
+        if picked != self.entity {
+            self.entity = picked;
+            self.held = 0.;
+        }
+    }
+}
+
+/// how well a tower leads its shots (1 aims exactly at the intercept point, 0 aims where the
+/// target is now with up to a unit of random spread)
+#[derive(Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct Accuracy {
+    pub accuracy: f32,
+}
+
+impl Default for Accuracy {
+    fn default() -> Self {
+        Self { accuracy: 1. }
+    }
+}
+
+impl Accuracy {
+    pub fn aim(&self, target: Vec3, intercept: Vec3, rng: &mut impl Rng) -> Vec3 {
+        let accuracy = self.accuracy.clamp(0., 1.);
+        let spread = 1. - accuracy;
+        let jitter = Vec3::new(
+            rng.gen_range(-1.0..=1.0),
+            0.,
+            rng.gen_range(-1.0..=1.0),
+        ) * spread;
+        target.lerp(intercept, accuracy) + jitter
+    }
+}
+
+/// where to aim a projectile fired at `speed` from `origin` so that it meets a target moving at a
+/// constant `velocity` (`None` if the target is too fast to ever be caught)
+pub fn intercept(origin: Vec3, target: Vec3, velocity: Vec3, speed: f32) -> Option<Vec3> {
+    let offset = target - origin;
+    // |offset + velocity * t| = speed * t
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2. * offset.dot(velocity);
+    let c = offset.length_squared();
+    let time = if a.abs() < f32::EPSILON {
+        // same speed as the projectile: only catchable if it's coming towards us
+        if b >= 0. {
+            return None;
+        }
+        -c / b
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (t1, t2) = ((-b - root) / (2. * a), (-b + root) / (2. * a));
+        match (t1 > 0., t2 > 0.) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return None,
+        }
+    };
+    Some(target + velocity * time)
+}
+
+/// an enemy in range of a tower and the stats the targeting modes compare
+#[derive(Clone, Debug)]
+pub struct TargetCandidate {
+    pub entity: Entity,
+    /// from the tower
+    pub distance: f32,
+    /// from the goal (along the path round any walls for ground enemies)
+    pub goal_distance: f32,
+    pub hp: usize,
+    pub max_hp: usize,
+    /// current speed including slows
+    pub speed: f32,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn acquire_targets(
+    time: Res<Time>,
+    mut tower_query: Query<
+        (
+            &Transform,
+            &tower::Range,
+            Option<&aura::Buffs>,
+            &tower::TargetLayers,
+            &TargetingMode,
+            &RetargetRule,
+            &mut CurrentTarget,
+        ),
+        With<tower::Tower>,
+    >,
+    enemy_query: Query<
+        (
+            Entity,
+            &Transform,
+            &health::HealthPoints,
+            &enemy::Speed,
+            Option<&status::StatusEffects>,
+            Option<&enemy::Flying>,
+        ),
+        With<enemy::Enemy>,
+    >,
+    goal_query: Query<&Transform, With<goal::Goal>>,
+    flow_field: Res<flow_field::FlowField>,
+) {
+    let rng = &mut rand::thread_rng();
+    let goal = goal_query
+        .get_single()
+        .map(|t| t.translation)
+        .unwrap_or(Vec3::ZERO);
+    for (transform, range, buffs, layers, mode, rule, mut current) in tower_query.iter_mut() {
+        let range = range.range * buffs.map_or(1., |buffs| buffs.range);
+        let candidates: Vec<TargetCandidate> = enemy_query
+            .iter()
+            .filter(|(_, _, hp, _, _, flying)| hp.hp > 0 && layers.can_target(flying.is_some()))
+            .filter_map(|(entity, enemy_transform, hp, speed, status, flying)| {
+                let distance = (enemy_transform.translation - transform.translation).length();
+                if distance >= range {
+                    return None;
+                }
+                // ground enemies are as far from the goal as the walk around the walls
+                let position =
+                    Vec2::new(enemy_transform.translation.x, enemy_transform.translation.z);
+                let straight = (Vec2::new(goal.x, goal.z) - position).length();
+                let goal_distance = match flying {
+                    Some(_) => straight,
+                    None => flow_field.path_distance(position).unwrap_or(straight),
+                };
+                Some(TargetCandidate {
+                    entity,
+                    distance,
+                    goal_distance,
+                    hp: hp.hp,
+                    max_hp: hp.max_hp,
+                    speed: speed.speed * status.map_or(1., |s| s.speed_multiplier()),
+                })
+            })
+            .collect();
+        current.update(*mode, *rule, &candidates, time.delta_seconds(), rng);
+    }
+}
+
+/// R cycles the targeting mode of the selected towers (dropping their current target)
+pub fn cycle_targeting_mode(
+    keys: Res<Input<KeyCode>>,
+    mut query: Query<(&Selection, &mut TargetingMode, &mut CurrentTarget), With<tower::Tower>>,
+) {
+    if !keys.just_pressed(KeyCode::R) {
+        return;
+    }
+    for (selection, mut mode, mut current) in query.iter_mut() {
+        if selection.selected() {
+            *mode = mode.next();
+            current.entity = None;
+            info!("tower now targets {:?}", *mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(index: u32, distance: f32, goal_distance: f32, hp: usize) -> TargetCandidate {
+        TargetCandidate {
+            entity: Entity::from_raw(index),
+            distance,
+            goal_distance,
+            hp,
+            max_hp: 10 * index as usize,
+            speed: index as f32,
+        }
+    }
+
+    #[test]
+    fn modes_pick_the_expected_enemy() {
+        let candidates = [
+            candidate(1, 5., 10., 8),
+            candidate(2, 2., 20., 3),
+            candidate(3, 9., 4., 12),
+        ];
+        let rng = &mut rand::thread_rng();
+        let pick = |mode: TargetingMode, rng: &mut rand::rngs::ThreadRng| {
+            mode.pick(&candidates, rng).unwrap().entity.index()
+        };
+        assert_eq!(pick(TargetingMode::Closest, rng), 2);
+        assert_eq!(pick(TargetingMode::First, rng), 3);
+        assert_eq!(pick(TargetingMode::Last, rng), 2);
+        assert_eq!(pick(TargetingMode::Strongest, rng), 3);
+        assert_eq!(pick(TargetingMode::Weakest, rng), 2);
+        assert_eq!(pick(TargetingMode::MostHp, rng), 3);
+        assert_eq!(pick(TargetingMode::Fastest, rng), 3);
+        assert!(TargetingMode::Random.pick(&candidates, rng).is_some());
+        assert!(TargetingMode::Closest.pick(&[], rng).is_none());
+    }
+
+    #[test]
+    fn lock_keeps_target_until_it_leaves() {
+        let rng = &mut rand::thread_rng();
+        let mut current = CurrentTarget::default();
+        let mode = TargetingMode::Closest;
+        current.update(mode, RetargetRule::Lock, &[candidate(1, 5., 0., 1)], 0.1, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(1)));
+        let candidates = [candidate(1, 5., 0., 1), candidate(2, 1., 0., 1)];
+        current.update(mode, RetargetRule::Lock, &candidates, 0.1, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(1)));
+        current.update(mode, RetargetRule::Always, &candidates, 0.1, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(2)));
+        current.update(mode, RetargetRule::Lock, &[candidate(3, 5., 0., 1)], 0.1, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(3)));
+        current.update(mode, RetargetRule::Lock, &[], 0.1, rng);
+        assert_eq!(current.entity, None);
+    }
+
+    #[test]
+    fn after_rule_retargets_once_held_long_enough() {
+        let rng = &mut rand::thread_rng();
+        let mut current = CurrentTarget::default();
+        let mode = TargetingMode::Closest;
+        let rule = RetargetRule::After(1.);
+        current.update(mode, rule, &[candidate(1, 5., 0., 1)], 0.1, rng);
+        let candidates = [candidate(1, 5., 0., 1), candidate(2, 1., 0., 1)];
+        current.update(mode, rule, &candidates, 0.5, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(1)));
+        current.update(mode, rule, &candidates, 0.6, rng);
+        assert_eq!(current.entity, Some(Entity::from_raw(2)));
+        assert_eq!(current.held, 0.);
+    }
+
+    #[test]
+    fn intercept_leads_a_moving_target() {
+        let origin = Vec3::ZERO;
+        let target = Vec3::new(10., 0., 0.);
+        let velocity = Vec3::new(0., 0., 1.);
+        let aim = intercept(origin, target, velocity, 10.).unwrap();
+        let time = (aim - target).length() / velocity.length();
+        assert!(((aim - origin).length() - 10. * time).abs() < 1e-3);
+        assert!(aim.z > 0.);
+    }
+
+    #[test]
+    fn intercept_of_a_still_target_is_the_target() {
+        let target = Vec3::new(3., 1., 4.);
+        assert_eq!(intercept(Vec3::ZERO, target, Vec3::ZERO, 10.), Some(target));
+    }
+
+    #[test]
+    fn intercept_fails_against_faster_targets_running_away() {
+        let aim = intercept(Vec3::ZERO, Vec3::X, Vec3::X * 20., 10.);
+        assert_eq!(aim, None);
+    }
+
+    #[test]
+    fn perfect_accuracy_aims_at_the_intercept() {
+        let rng = &mut rand::thread_rng();
+        let aim = Accuracy::default().aim(Vec3::ZERO, Vec3::X, rng);
+        assert_eq!(aim, Vec3::X);
+    }
+
+    #[test]
+    fn next_cycles_through_every_mode() {
+        let mut mode = TargetingMode::default();
+        for _ in 0..TargetingMode::ALL.len() {
+            mode = mode.next();
+        }
+        assert_eq!(mode, TargetingMode::default());
+    }
+}
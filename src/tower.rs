@@ -1,10 +1,21 @@
 use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
 use bevy_mod_picking::{PickableBundle, Selection};
+use serde::Deserialize;
 
 use crate::assets;
+use crate::aura;
+use crate::damage;
+use crate::economy;
 use crate::enemy;
+use crate::goal;
 use crate::orb;
 use crate::grid;
+use crate::status;
+use crate::targeting;
+use crate::turret;
+use crate::upgrade;
+use crate::veterancy;
 
 pub struct TowerPlugin;
 impl Plugin for TowerPlugin {
@@ -12,15 +23,40 @@ impl Plugin for TowerPlugin {
         app.register_type::<Tower>()
             .register_type::<Shooting>()
             .register_type::<Range>()
-            .register_type::<Damage>()
+            .register_type::<TargetLayers>()
+            .register_type::<Projectile>()
+            .add_asset::<TowerDefinitions>()
+            .add_asset_loader(assets::RonAssetLoader::<TowerDefinitions>::new(&["towers.ron"]))
+            .init_resource::<SelectedTowerType>()
+            .add_startup_system(spawn_tower_type_text)
+            .add_system(select_tower_type)
+            .add_system(update_tower_type_text)
             .add_system(spawn_tower)
-            .add_system(tower_shoot);
+            .add_system(spawn_default_towers)
+            .add_system(tower_shoot.run_if(in_state(goal::GameState::Playing)));
     }
 }
 
-#[derive(Clone, Component, Default, Reflect)]
+/// id of the tower definition this tower was built from
+#[derive(Clone, Component, Reflect)]
 #[reflect(Component)]
-pub struct Tower;
+pub struct Tower {
+    pub id: String,
+    /// gold spent on the tower and its upgrades
+    pub invested: usize,
+    /// the grid cell the tower stands on
+    pub cell: Entity,
+}
+
+impl Default for Tower {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            invested: 0,
+            cell: Entity::PLACEHOLDER,
+        }
+    }
+}
 
 #[derive(Clone, Component, Default, Reflect)]
 #[reflect(Component)]
@@ -34,122 +70,503 @@ pub struct Range {
     pub range: f32,
 }
 
+/// which movement layers a tower is able to shoot at
+#[derive(Clone, Component, Debug, Deserialize, Reflect)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct TargetLayers {
+    pub ground: bool,
+    pub air: bool,
+}
+
+impl Default for TargetLayers {
+    fn default() -> Self {
+        Self {
+            ground: true,
+            air: false,
+        }
+    }
+}
+
+/// what a tower fires (shared mesh and material for every orb the tower shoots)
 #[derive(Clone, Component, Default, Reflect)]
 #[reflect(Component)]
-pub struct Damage {
-    pub hp: usize,
+pub struct Projectile {
+    pub speed: f32,
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl TargetLayers {
+    pub fn can_target(&self, flying: bool) -> bool {
+        if flying {
+            self.air
+        } else {
+            self.ground
+        }
+    }
+}
+
+/// every tower type, loaded from a `*.towers.ron` file (number keys pick them in this order)
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "5c1b2f0e-8d7a-4b4e-9f3c-2a6e1d0b7c44"]
+pub struct TowerDefinitions {
+    pub towers: Vec<TowerDefinition>,
+    /// fraction (0 to 1) of the invested gold given back when a tower is sold
+    #[serde(default = "default_sell_refund")]
+    pub sell_refund: f32,
+}
+
+fn default_sell_refund() -> f32 {
+    0.75
+}
+
+impl TowerDefinitions {
+    pub fn get(&self, id: &str) -> Option<&TowerDefinition> {
+        self.towers.iter().find(|t| t.id == id)
+    }
+
+    /// where the tower type sits in the list (its number key is one more)
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.towers.iter().position(|t| t.id == id)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TowerDefinition {
+    pub id: String,
+    pub name: String,
+    /// asset path of the tower model (the default tower model if left out)
+    #[serde(default)]
+    pub mesh: Option<String>,
+    pub color: [f32; 3],
+    pub cost: usize,
+    pub range: f32,
+    /// shots per second (support towers leave it out and don't shoot)
+    #[serde(default)]
+    pub fire_rate: f32,
+    #[serde(default)]
+    pub damage: usize,
+    #[serde(default)]
+    pub damage_kind: damage::DamageKind,
+    #[serde(default)]
+    pub shield_bonus: f32,
+    #[serde(default)]
+    pub critical: Option<damage::Critical>,
+    #[serde(default)]
+    pub on_hit: Vec<status::StatusEffect>,
+    #[serde(default)]
+    pub projectile: ProjectileDefinition,
+    #[serde(default)]
+    pub layers: TargetLayers,
+    #[serde(default)]
+    pub targeting: targeting::TargetingMode,
+    #[serde(default)]
+    pub retarget: targeting::RetargetRule,
+    #[serde(default = "default_accuracy")]
+    pub accuracy: f32,
+    /// how fast the barrel turns, in degrees per second
+    #[serde(default = "default_turn_rate")]
+    pub turn_rate: f32,
+    /// buffs for towers and effects on enemies within `range`
+    #[serde(default)]
+    pub aura: Option<aura::AuraDefinition>,
+    /// first tier of the upgrade tree
+    #[serde(default)]
+    pub upgrades: Vec<upgrade::UpgradeDefinition>,
+}
+
+fn default_accuracy() -> f32 {
+    1.
+}
+
+fn default_turn_rate() -> f32 {
+    360.
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ProjectileDefinition {
+    pub speed: f32,
+    pub size: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for ProjectileDefinition {
+    fn default() -> Self {
+        Self {
+            speed: 10.,
+            size: 0.1,
+            color: [0.8, 0.1, 0.1],
+        }
+    }
 }
 
+/// the tower type placed by `T` (the first one until a number key picks another)
+#[derive(Resource, Default)]
+pub struct SelectedTowerType {
+    pub id: String,
+}
+
+#[derive(Component)]
+pub struct TowerTypeText;
+
+/// builds the tower with the given id (the single place towers are put together)
+/// `invested` is the gold actually paid for it, which selling refunds a share of
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_tower_by_id(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    game_assets: &assets::GameAssets,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    definitions: &TowerDefinitions,
+    id: &str,
+    cell: Entity,
+    location: Vec3,
+    invested: usize,
+) -> Option<Entity> {
+    let Some(definition) = definitions.get(id) else {
+        warn!("unknown tower type {:?}", id);
+        return None;
+    };
+    let [r, g, b] = definition.color;
+    let [pr, pg, pb] = definition.projectile.color;
+    // the base and barrel share a material so upgrades recolour both
+    let material = materials.add(Color::rgb(r, g, b).into());
+    let mut tower = commands.spawn((
+        PbrBundle {
+            mesh: match &definition.mesh {
+                Some(path) => asset_server.load(path.as_str()),
+                None => game_assets.tower_mesh.clone(),
+            },
+            material: material.clone(),
+            transform: Transform::from_translation(location + Vec3::new(0., 0.1, 0.)),
+            ..Default::default()
+        },
+        Tower {
+            id: definition.id.clone(),
+            invested,
+            cell,
+        },
+        Name::new(definition.name.clone()),
+        Range {
+            range: definition.range,
+        },
+        aura::Buffs::default(),
+        upgrade::Upgrades::default(),
+        veterancy::Veterancy::default(),
+        PickableBundle::default(),
+    ));
+    if let Some(aura) = &definition.aura {
+        tower.insert(aura::Aura::new(aura));
+    }
+    if definition.fire_rate <= 0. {
+        return Some(tower.id());
+    }
+    tower.insert((
+        Shooting {
+            // stays finished until the turret is aligned and the shot is taken
+            timer: Timer::from_seconds(1. / definition.fire_rate, TimerMode::Once),
+        },
+        damage::Damage {
+            amount: definition.damage,
+            kind: definition.damage_kind,
+            shield_bonus: definition.shield_bonus,
+        },
+        Projectile {
+            speed: definition.projectile.speed,
+            mesh: meshes.add(Mesh::from(shape::Cube {
+                size: definition.projectile.size,
+            })),
+            material: materials.add(Color::rgb(pr, pg, pb).into()),
+        },
+        definition.layers.clone(),
+        definition.targeting,
+        definition.retarget,
+        targeting::CurrentTarget::default(),
+        targeting::Accuracy {
+            accuracy: definition.accuracy,
+        },
+        turret::Turret {
+            turn_rate: definition.turn_rate,
+            ..Default::default()
+        },
+    ));
+    tower.with_children(|parent| {
+        parent.spawn((
+            PbrBundle {
+                mesh: meshes.add(turret::barrel_mesh()),
+                material,
+                transform: Transform::from_xyz(0., turret::BARREL_HEIGHT, 0.),
+                ..Default::default()
+            },
+            turret::Barrel,
+            Name::new("Barrel"),
+        ));
+    });
+    if let Some(critical) = &definition.critical {
+        tower.insert(critical.clone());
+    }
+    if !definition.on_hit.is_empty() {
+        tower.insert(status::OnHit {
+            effects: definition.on_hit.clone(),
+        });
+    }
+    Some(tower.id())
+}
+
+/// number keys choose which tower type `T` places
+pub fn select_tower_type(
+    keys: Res<Input<KeyCode>>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    mut selected: ResMut<SelectedTowerType>,
+) {
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    let number_keys = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    // also falls back to the first type if the selected one is gone after a reload
+    if definitions.get(&selected.id).is_none() {
+        if let Some(first) = definitions.towers.first() {
+            selected.id = first.id.clone();
+        }
+    }
+    for (index, key) in number_keys.iter().enumerate() {
+        if !keys.just_pressed(*key) {
+            continue;
+        }
+        if let Some(definition) = definitions.towers.get(index) {
+            selected.id = definition.id.clone();
+        }
+    }
+}
+
+pub fn spawn_tower_type_text(mut commands: Commands, game_assets: Res<assets::GameAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: game_assets.font.clone(),
+                font_size: 18.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(10.),
+                top: Val::Px(10.),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        TowerTypeText,
+        Name::new("TowerTypeText"),
+    ));
+}
+
+pub fn update_tower_type_text(
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    selected: Res<SelectedTowerType>,
+    mut query: Query<&mut Text, With<TowerTypeText>>,
+) {
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    let (Some(index), Some(definition)) = (
+        definitions.position(&selected.id),
+        definitions.get(&selected.id),
+    ) else {
+        return;
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "[{}] {} ({} gold)",
+            index + 1,
+            definition.name,
+            definition.cost
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_tower(
     keys: Res<Input<KeyCode>>,
     mut commands: Commands,
-    assets: Res<assets::GameAssets>,
+    asset_server: Res<AssetServer>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    selected_type: Res<SelectedTowerType>,
+    mut wallet: ResMut<economy::Wallet>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    selected: Query<(&Transform, &Selection)>,
+    mut selected: Query<(Entity, &Transform, &Selection, &mut grid::Cell)>,
+    mut spent_events: EventWriter<economy::GoldSpent>,
 ) {
-    for key in keys.get_just_pressed() {
-        match key {
-            KeyCode::T => {
-                let color = Color::rgba(0., 0.7, 0.7, 255.);
-                let location = selected
-                    .iter()
-                    .filter(|t| t.1.selected())
-                    .map(|t| t.0.translation)
-                    .next();
-                match location{
-                    Some(location) => {
-                        commands.spawn((
-                            PbrBundle {
-                                mesh: assets.tower_mesh.clone(),
-                                material: materials.add(color.into()),
-                                transform: Transform::from_translation(location + Vec3::new(0., 0.1, 0.)),
-                                ..Default::default()
-                            },
-                            Tower,
-                            Name::new("Tower"),
-                            Shooting {
-                                timer: Timer::from_seconds(1.0, TimerMode::Repeating),
-                            },
-                            Range { range: 15.0 },
-                            Damage { hp: 5 },
-                            PickableBundle::default(),
-                        ));
-                    }
-                    None => { continue; }
-                }
-            }
-            _ => {}
-        }
+    if !keys.just_pressed(KeyCode::T) {
+        return;
+    }
+    let Some((cell_entity, cell_transform, _, mut cell)) =
+        selected.iter_mut().find(|t| t.2.selected())
+    else {
+        return;
+    };
+    if cell.is_occupied {
+        info!("there is already a tower on this cell");
+        return;
+    }
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    let Some(definition) = definitions.get(&selected_type.id) else {
+        return;
+    };
+    if !wallet.try_spend(definition.cost) {
+        info!("not enough gold for a {} tower ({} gold)", definition.name, definition.cost);
+        return;
     }
+    spent_events.send(economy::GoldSpent {
+        gold: definition.cost,
+        on: economy::Purchase::Tower,
+    });
+    spawn_tower_by_id(
+        &mut commands,
+        &asset_server,
+        &game_assets,
+        &mut meshes,
+        &mut materials,
+        definitions,
+        &definition.id,
+        cell_entity,
+        cell_transform.translation,
+        definition.cost,
+    );
+    cell.is_occupied = true;
 }
 
 
+/// spawns basic towers near the origin once the tower definitions have loaded
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_default_towers(
     mut commands: Commands,
+    mut spawned: Local<bool>,
+    asset_server: Res<AssetServer>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<TowerDefinitions>>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    assets: Res<assets::GameAssets>,
-    cell_query: Query<&Transform, With<grid::Cell>>,
+    mut cell_query: Query<(Entity, &Transform, &mut grid::Cell)>,
 ) {
-    for (_ux, cell) in cell_query.iter().enumerate() {
-        if cell.translation.x.abs() < 3. && cell.translation.z.abs() < 3. {
-            let color = Color::rgba(0., 0.7, 0.7, 255.);
-            let location = cell.translation;
-            commands.spawn((
-                PbrBundle {
-                    mesh: assets.tower_mesh.clone(),
-                    material: materials.add(color.into()),
-                    transform: Transform::from_translation(location + Vec3::new(0., 0.1, 0.)),
-                    ..Default::default()
-                },
-                Tower,
-                Shooting {
-                    timer: Timer::from_seconds(1.0, TimerMode::Repeating),
-                },
-                Damage { hp: 5 },
-                Range { range: 15.0 },
-                PickableBundle::default(),
-                Name::new("Tower"),
-            ));
+    if *spawned {
+        return;
+    }
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    if definitions.get("basic").is_none() {
+        warn!("no basic tower to place by default");
+        *spawned = true;
+        return;
+    }
+    for (cell_entity, transform, mut cell) in cell_query.iter_mut() {
+        if transform.translation.x.abs() < 3. && transform.translation.z.abs() < 3. {
+            spawn_tower_by_id(
+                &mut commands,
+                &asset_server,
+                &game_assets,
+                &mut meshes,
+                &mut materials,
+                definitions,
+                "basic",
+                cell_entity,
+                transform.translation,
+                // free towers are worth nothing when sold
+                0,
+            );
+            cell.is_occupied = true;
         }
     }
+    *spawned = true;
 }
 
 
+#[allow(clippy::type_complexity)]
 pub fn tower_shoot(
     mut commands: Commands,
     time: Res<Time>,
-    mut tower_query: Query<(&mut Shooting, &Transform, &Range, Option<&Damage>), With<Tower>>,
-    mut enemy_query: Query<&Transform, With<enemy::Enemy>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut tower_query: Query<
+        (
+            Entity,
+            &mut Shooting,
+            &Transform,
+            &targeting::CurrentTarget,
+            &Projectile,
+            Option<&turret::Turret>,
+            Option<&aura::Buffs>,
+            Option<&targeting::Accuracy>,
+            Option<&damage::Damage>,
+            Option<&damage::Critical>,
+            Option<&status::OnHit>,
+        ),
+        With<Tower>,
+    >,
+    enemy_query: Query<(&Transform, Option<&enemy::Velocity>), With<enemy::Enemy>>,
 ) {
-    for (mut shooting, transform, range, damage) in tower_query.iter_mut() {
-        shooting.timer.tick(time.delta());
-        if shooting.timer.finished() {
-            // find the closest enemy
-            let mut target: Option<&Transform> = None;
-            for enemy_transform in enemy_query.iter_mut() {
-                let distance = (enemy_transform.translation - transform.translation).length();
-                if distance < range.range {
-                    match target {
-                        Some(targ) => {
-                            if distance < (targ.translation - transform.translation).length() {
-                                target = Some(enemy_transform);
-                            }
-                        }
-                        None => {
-                            target = Some(enemy_transform);
-                        }
-                    }
-                }
-            }
-            if let Some(targ) = target {
-                let start_pt = transform.translation + Vec3::new(0.0, 2., 0.);
-                let target_pt = targ.translation;
+    let rng = &mut rand::thread_rng();
+    for (
+        tower,
+        mut shooting,
+        transform,
+        current_target,
+        projectile,
+        turret,
+        buffs,
+        accuracy,
+        damage,
+        critical,
+        on_hit,
+    ) in tower_query.iter_mut()
+    {
+        let buffs = buffs.copied().unwrap_or_default();
+        shooting.timer.tick(time.delta().mul_f32(buffs.fire_rate));
+        // a turret holds its shot until its barrel has turned to the target
+        let aligned = match turret {
+            Some(turret) => turret.aligned,
+            None => true,
+        };
+        if shooting.timer.finished() && aligned {
+            let target = current_target.entity.and_then(|e| enemy_query.get(e).ok());
+            if let Some((targ, velocity)) = target {
+                shooting.timer.reset();
+                // aim in 3d so shots at flying enemies climb to their altitude
+                let start_pt = match turret {
+                    Some(turret) => turret.muzzle,
+                    None => transform.translation + Vec3::new(0.0, 2., 0.),
+                };
+                let velocity = velocity.map_or(Vec3::ZERO, |v| v.velocity);
+                let lead = targeting::intercept(start_pt, targ.translation, velocity, projectile.speed)
+                    .unwrap_or(targ.translation);
+                let target_pt = accuracy
+                    .cloned()
+                    .unwrap_or_default()
+                    .aim(targ.translation, lead, rng);
                 let mut orb = commands.spawn((
                     PbrBundle {
-                        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.1 })),
-                        material: materials.add(Color::rgb(0.8, 0.1, 0.1).into()),
+                        mesh: projectile.mesh.clone(),
+                        material: projectile.material.clone(),
                         transform: Transform::from_translation(start_pt)
                             .looking_at(target_pt, Vec3::Y),
                         ..Default::default()
@@ -157,11 +574,21 @@ pub fn tower_shoot(
                     orb::Orb {
                         direction: (target_pt - start_pt).normalize(),
                         target: target_pt,
-                        speed: 10.0,
+                        speed: projectile.speed,
+                        lifetime: Timer::from_seconds(5.0, TimerMode::Once),
+                        source: Some(tower),
                     },
                 ));
                 if let Some(dmg) = damage {
-                    orb.insert(dmg.clone());
+                    let mut dmg = dmg.clone();
+                    dmg.amount = (dmg.amount as f32 * buffs.damage).round() as usize;
+                    orb.insert(dmg);
+                }
+                if let Some(critical) = critical {
+                    orb.insert(critical.clone());
+                }
+                if let Some(on_hit) = on_hit {
+                    orb.insert(on_hit.clone());
                 }
             }
         }
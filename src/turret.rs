@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+use crate::enemy;
+use crate::goal;
+use crate::targeting;
+use crate::tower;
+
+/// height of the barrel's pivot above the tower's origin
+pub const BARREL_HEIGHT: f32 = 2.1;
+pub const BARREL_LENGTH: f32 = 0.9;
+/// how far (in radians) the barrel may point away from its target and still fire
+const ALIGNED_TOLERANCE: f32 = 0.15;
+
+pub struct TurretPlugin;
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Turret>()
+            .register_type::<Barrel>()
+            .add_system(
+                aim_turrets
+                    .after(targeting::acquire_targets)
+                    .before(tower::tower_shoot)
+                    .run_if(in_state(goal::GameState::Playing)),
+            );
+    }
+}
+
+/// how a tower turns its barrel (the tower only fires once `aligned`)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Turret {
+    /// degrees per second
+    pub turn_rate: f32,
+    pub yaw: f32,
+    pub aligned: bool,
+    /// world position of the barrel's tip, where shots start
+    pub muzzle: Vec3,
+}
+
+/// the rotating child of a tower
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Barrel;
+
+/// barrel mesh pivoting at its back end and pointing down -z
+pub fn barrel_mesh() -> Mesh {
+    Mesh::from(shape::Box {
+        min_x: -0.08,
+        max_x: 0.08,
+        min_y: -0.08,
+        max_y: 0.08,
+        min_z: -BARREL_LENGTH,
+        max_z: 0.2,
+    })
+}
+
+/// the yaw that points -z (forward) along `direction`
+pub fn yaw_towards(direction: Vec3) -> f32 {
+    (-direction.x).atan2(-direction.z)
+}
+
+/// signed smallest angle from `from` to `to`, between -PI and PI
+pub fn angle_between(from: f32, to: f32) -> f32 {
+    let diff = (to - from).rem_euclid(TAU);
+    if diff > PI {
+        diff - TAU
+    } else {
+        diff
+    }
+}
+
+/// turns `current` towards `desired` by at most `max_step` (the short way round)
+pub fn turn_towards(current: f32, desired: f32, max_step: f32) -> f32 {
+    let diff = angle_between(current, desired);
+    if diff.abs() <= max_step {
+        desired
+    } else {
+        current + max_step * diff.signum()
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn aim_turrets(
+    time: Res<Time>,
+    mut towers: Query<(&Transform, &targeting::CurrentTarget, &mut Turret), Without<Barrel>>,
+    mut barrels: Query<(&Parent, &mut Transform), With<Barrel>>,
+    enemies: Query<&Transform, (With<enemy::Enemy>, Without<Barrel>, Without<Turret>)>,
+) {
+    for (parent, mut barrel_transform) in barrels.iter_mut() {
+        let Ok((tower_transform, current_target, mut turret)) = towers.get_mut(parent.get()) else {
+            continue;
+        };
+        let target = current_target.entity.and_then(|e| enemies.get(e).ok());
+        match target {
+            Some(target) => {
+                let desired = yaw_towards(target.translation - tower_transform.translation);
+                let max_step = turret.turn_rate.to_radians() * time.delta_seconds();
+                turret.yaw = turn_towards(turret.yaw, desired, max_step);
+                turret.aligned = angle_between(turret.yaw, desired).abs() <= ALIGNED_TOLERANCE;
+            }
+            None => turret.aligned = false,
+        }
+        barrel_transform.rotation = Quat::from_rotation_y(turret.yaw);
+        turret.muzzle = tower_transform
+            .transform_point(barrel_transform.transform_point(Vec3::new(0., 0., -BARREL_LENGTH)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaw_points_forward_at_direction() {
+        for direction in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::new(1., 0., -1.)] {
+            let forward = Quat::from_rotation_y(yaw_towards(direction)) * Vec3::NEG_Z;
+            assert!((forward - direction.normalize()).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn turns_the_short_way_round() {
+        let current = 170_f32.to_radians();
+        let desired = -170_f32.to_radians();
+        let turned = turn_towards(current, desired, 5_f32.to_radians());
+        assert!((angle_between(turned, 175_f32.to_radians())).abs() < 1e-5);
+        assert_eq!(turn_towards(current, desired, 1.), desired);
+    }
+}
@@ -0,0 +1,398 @@
+use bevy::prelude::*;
+use bevy_mod_picking::Selection;
+use serde::Deserialize;
+
+use crate::assets;
+use crate::aura;
+use crate::damage;
+use crate::economy;
+use crate::status;
+use crate::targeting;
+use crate::tower;
+use crate::veterancy;
+
+pub struct UpgradePlugin;
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Upgrades>()
+            .add_event::<TowerUpgraded>()
+            .add_startup_system(spawn_upgrade_text)
+            .add_system(upgrade_selected_tower)
+            .add_system(apply_upgrades.after(upgrade_selected_tower))
+            .add_system(update_upgrade_text);
+    }
+}
+
+/// one node of a tower's upgrade tree (buying it unlocks its `next` options and locks its siblings)
+#[derive(Clone, Debug, Deserialize)]
+pub struct UpgradeDefinition {
+    pub name: String,
+    pub cost: usize,
+    pub modifiers: Vec<UpgradeModifier>,
+    /// new tower colour from this tier on
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    #[serde(default)]
+    pub next: Vec<UpgradeDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum UpgradeModifier {
+    /// added to the range
+    Range(f32),
+    /// multiplies shots per second
+    FireRate(f32),
+    /// added to the damage
+    Damage(usize),
+    DamageKind(damage::DamageKind),
+    /// added to the damage's shield bonus
+    ShieldBonus(f32),
+    /// replaces the tower's crits
+    Critical(damage::Critical),
+    /// adds an effect to the tower's on-hit effects
+    OnHit(status::StatusEffect),
+    /// multiplies the projectile speed
+    ProjectileSpeed(f32),
+    Accuracy(f32),
+    Layers(tower::TargetLayers),
+    /// replaces the buffs a support tower's aura gives
+    AuraBuffs(aura::Buffs),
+}
+
+/// the time between shots once the fire rate is multiplied by `multiplier`
+/// (none for a multiplier that isn't positive, or is too small to give an interval at all)
+pub fn shot_interval(
+    interval: std::time::Duration,
+    multiplier: f32,
+) -> Option<std::time::Duration> {
+    if !multiplier.is_finite() || multiplier <= 0. {
+        return None;
+    }
+    std::time::Duration::try_from_secs_f32(interval.as_secs_f32() / multiplier).ok()
+}
+
+/// the branch taken at each tier of the tower's upgrade tree
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Upgrades {
+    pub path: Vec<usize>,
+}
+
+impl Upgrades {
+    pub fn tier(&self) -> usize {
+        self.path.len()
+    }
+
+    /// the upgrades that can be bought next
+    pub fn options<'a>(&self, tree: &'a [UpgradeDefinition]) -> &'a [UpgradeDefinition] {
+        let mut options = tree;
+        for &branch in self.path.iter() {
+            match options.get(branch) {
+                Some(upgrade) => options = &upgrade.next,
+                None => return &[],
+            }
+        }
+        options
+    }
+}
+
+/// sent once an upgrade has been bought (the tower's components are updated by `apply_upgrades`)
+pub struct TowerUpgraded {
+    pub tower: Entity,
+    pub upgrade: UpgradeDefinition,
+    pub tier: usize,
+}
+
+#[derive(Component)]
+pub struct UpgradeText;
+
+const UPGRADE_KEYS: [KeyCode; 3] = [KeyCode::U, KeyCode::I, KeyCode::O];
+
+/// U, I and O buy the first, second and third upgrade option of the selected tower
+pub fn upgrade_selected_tower(
+    keys: Res<Input<KeyCode>>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<tower::TowerDefinitions>>,
+    mut wallet: ResMut<economy::Wallet>,
+    mut query: Query<(Entity, &mut tower::Tower, &Selection, &mut Upgrades)>,
+    mut upgraded_events: EventWriter<TowerUpgraded>,
+    mut spent_events: EventWriter<economy::GoldSpent>,
+) {
+    let Some(branch) = UPGRADE_KEYS.iter().position(|key| keys.just_pressed(*key)) else {
+        return;
+    };
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    for (entity, mut tower, selection, mut upgrades) in query.iter_mut() {
+        if !selection.selected() {
+            continue;
+        }
+        let Some(definition) = definitions.get(&tower.id) else {
+            continue;
+        };
+        let Some(upgrade) = upgrades.options(&definition.upgrades).get(branch).cloned() else {
+            continue;
+        };
+        if !wallet.try_spend(upgrade.cost) {
+            info!("not enough gold for {} ({} gold)", upgrade.name, upgrade.cost);
+            continue;
+        }
+        spent_events.send(economy::GoldSpent {
+            gold: upgrade.cost,
+            on: economy::Purchase::Upgrade,
+        });
+        upgrades.path.push(branch);
+        tower.invested += upgrade.cost;
+        upgraded_events.send(TowerUpgraded {
+            tower: entity,
+            upgrade,
+            tier: upgrades.tier(),
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn apply_upgrades(
+    mut upgraded_events: EventReader<TowerUpgraded>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut query: Query<(
+        &mut tower::Range,
+        &mut Transform,
+        &Handle<StandardMaterial>,
+        Option<&mut tower::Shooting>,
+        Option<&mut tower::Projectile>,
+        Option<&mut tower::TargetLayers>,
+        Option<&mut targeting::Accuracy>,
+        Option<&mut damage::Damage>,
+        Option<&mut damage::Critical>,
+        Option<&mut status::OnHit>,
+        Option<&mut aura::Aura>,
+    )>,
+    mut commands: Commands,
+) {
+    for ev in upgraded_events.iter() {
+        let Ok((
+            mut range,
+            mut transform,
+            material,
+            mut shooting,
+            mut projectile,
+            mut layers,
+            mut accuracy,
+            mut damage,
+            mut critical,
+            mut on_hit,
+            mut aura,
+        )) = query.get_mut(ev.tower)
+        else {
+            continue;
+        };
+        for modifier in ev.upgrade.modifiers.iter() {
+            match modifier {
+                UpgradeModifier::Range(bonus) => range.range += bonus,
+                UpgradeModifier::FireRate(multiplier) => {
+                    if let Some(shooting) = shooting.as_mut() {
+                        match shot_interval(shooting.timer.duration(), *multiplier) {
+                            Some(interval) => shooting.timer.set_duration(interval),
+                            None => warn!(
+                                "{} has an invalid fire rate multiplier {}",
+                                ev.upgrade.name, multiplier
+                            ),
+                        }
+                    }
+                }
+                UpgradeModifier::Damage(bonus) => {
+                    if let Some(damage) = damage.as_mut() {
+                        damage.amount += bonus;
+                    }
+                }
+                UpgradeModifier::DamageKind(kind) => {
+                    if let Some(damage) = damage.as_mut() {
+                        damage.kind = *kind;
+                    }
+                }
+                UpgradeModifier::ShieldBonus(bonus) => {
+                    if let Some(damage) = damage.as_mut() {
+                        damage.shield_bonus += bonus;
+                    }
+                }
+                UpgradeModifier::Critical(new_critical) => match critical.as_mut() {
+                    Some(critical) => **critical = new_critical.clone(),
+                    None => {
+                        commands.entity(ev.tower).insert(new_critical.clone());
+                    }
+                },
+                UpgradeModifier::OnHit(effect) => match on_hit.as_mut() {
+                    Some(on_hit) => on_hit.effects.push(effect.clone()),
+                    None => {
+                        commands.entity(ev.tower).insert(status::OnHit {
+                            effects: vec![effect.clone()],
+                        });
+                    }
+                },
+                UpgradeModifier::ProjectileSpeed(multiplier) => {
+                    if let Some(projectile) = projectile.as_mut() {
+                        projectile.speed *= multiplier;
+                    }
+                }
+                UpgradeModifier::Accuracy(value) => {
+                    if let Some(accuracy) = accuracy.as_mut() {
+                        accuracy.accuracy = *value;
+                    }
+                }
+                UpgradeModifier::Layers(new_layers) => {
+                    if let Some(layers) = layers.as_mut() {
+                        **layers = new_layers.clone();
+                    }
+                }
+                UpgradeModifier::AuraBuffs(buffs) => {
+                    if let Some(aura) = aura.as_mut() {
+                        aura.buffs = *buffs;
+                    }
+                }
+            }
+        }
+        // every tier makes the tower a little bigger
+        transform.scale = Vec3::splat(1. + 0.1 * ev.tier as f32);
+        if let Some([r, g, b]) = ev.upgrade.color {
+            if let Some(material) = materials.get_mut(material) {
+                material.base_color = Color::rgb(r, g, b);
+            }
+        }
+        info!(
+            "upgraded {:?} to {} (tier {}, {} gold)",
+            ev.tower, ev.upgrade.name, ev.tier, ev.upgrade.cost
+        );
+    }
+}
+
+pub fn spawn_upgrade_text(mut commands: Commands, game_assets: Res<assets::GameAssets>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: game_assets.font.clone(),
+                font_size: 18.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(10.),
+                top: Val::Px(34.),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        UpgradeText,
+        Name::new("UpgradeText"),
+    ));
+}
+
+/// lists the upgrade options of the selected tower
+pub fn update_upgrade_text(
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<tower::TowerDefinitions>>,
+    towers: Query<(
+        &tower::Tower,
+        &Selection,
+        &Upgrades,
+        Option<&veterancy::Veterancy>,
+    )>,
+    mut query: Query<&mut Text, With<UpgradeText>>,
+) {
+    let Some(definitions) = definitions.get(&game_assets.towers) else {
+        return;
+    };
+    let selected = towers.iter().find(|(_, selection, _, _)| selection.selected());
+    let value = match selected {
+        Some((tower, _, upgrades, veterancy)) => {
+            let options = definitions
+                .get(&tower.id)
+                .map(|definition| upgrades.options(&definition.upgrades))
+                .unwrap_or(&[]);
+            let options: Vec<String> = options
+                .iter()
+                .zip(UPGRADE_KEYS.iter())
+                .map(|(upgrade, key)| {
+                    format!("[{:?}] {} ({} gold)", key, upgrade.name, upgrade.cost)
+                })
+                .collect();
+            let upgrades = if options.is_empty() {
+                format!("tier {} (fully upgraded)", upgrades.tier())
+            } else {
+                format!("tier {}: {}", upgrades.tier(), options.join("  "))
+            };
+            match veterancy {
+                Some(veterancy) => {
+                    let experience = veterancy.experience();
+                    let next = match veterancy::next_rank_in(experience) {
+                        Some(needed) => format!(", {} xp to next rank", needed),
+                        None => String::new(),
+                    };
+                    format!(
+                        "{}\nrank {} ({} kills, {} damage{})",
+                        upgrades, veterancy.rank, veterancy.kills, veterancy.damage, next
+                    )
+                }
+                None => upgrades,
+            }
+        }
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrade(name: &str, next: Vec<UpgradeDefinition>) -> UpgradeDefinition {
+        UpgradeDefinition {
+            name: name.to_string(),
+            cost: 0,
+            modifiers: vec![],
+            color: None,
+            next,
+        }
+    }
+
+    #[test]
+    fn fire_rate_shortens_the_interval() {
+        let interval = std::time::Duration::from_secs(1);
+        assert_eq!(
+            shot_interval(interval, 2.),
+            Some(std::time::Duration::from_millis(500))
+        );
+        assert_eq!(
+            shot_interval(interval, 0.5),
+            Some(std::time::Duration::from_secs(2))
+        );
+        for invalid in [0., -1., f32::NAN, f32::INFINITY, 1e-40] {
+            assert_eq!(shot_interval(interval, invalid), None);
+        }
+    }
+
+    #[test]
+    fn options_follow_the_chosen_branch() {
+        let tree = vec![
+            upgrade("a", vec![upgrade("a1", vec![]), upgrade("a2", vec![])]),
+            upgrade("b", vec![upgrade("b1", vec![])]),
+        ];
+        let mut upgrades = Upgrades::default();
+        assert_eq!(upgrades.options(&tree).len(), 2);
+        upgrades.path.push(1);
+        let options = upgrades.options(&tree);
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].name, "b1");
+        upgrades.path.push(0);
+        assert!(upgrades.options(&tree).is_empty());
+        assert_eq!(upgrades.tier(), 2);
+    }
+}
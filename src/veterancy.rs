@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::damage;
+use crate::health;
+use crate::tower;
+
+/// experience needed for each rank
+const RANK_EXPERIENCE: [usize; 4] = [100, 300, 700, 1500];
+/// experience for a kill on top of the damage dealt
+const KILL_EXPERIENCE: usize = 10;
+/// fire rate multiplier per rank
+const RANK_FIRE_RATE: f32 = 1.1;
+/// range added per rank
+const RANK_RANGE: f32 = 1.;
+
+pub struct VeterancyPlugin;
+impl Plugin for VeterancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Veterancy>()
+            .register_type::<RankPip>()
+            .add_event::<TowerPromoted>()
+            .add_startup_system(spawn_rank_pip_assets)
+            .add_system(
+                gain_experience
+                    .after(health::apply_damage)
+                    .after(health::regen_health),
+            )
+            .add_system(promote_towers.after(gain_experience))
+            .add_system(log_promotions);
+    }
+}
+
+/// what a tower has done (each point of damage is one experience, each kill a bit more)
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Veterancy {
+    pub kills: usize,
+    pub damage: usize,
+    pub rank: usize,
+}
+
+impl Veterancy {
+    pub fn experience(&self) -> usize {
+        self.damage + self.kills * KILL_EXPERIENCE
+    }
+}
+
+/// the rank earned with `experience`
+pub fn rank_for(experience: usize) -> usize {
+    RANK_EXPERIENCE
+        .iter()
+        .take_while(|needed| experience >= **needed)
+        .count()
+}
+
+/// experience still needed for the next rank (none at the top rank)
+pub fn next_rank_in(experience: usize) -> Option<usize> {
+    RANK_EXPERIENCE
+        .iter()
+        .find(|needed| experience < **needed)
+        .map(|needed| needed - experience)
+}
+
+/// one marker above a tower per rank
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct RankPip;
+
+#[derive(Resource)]
+pub struct RankPipAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+/// sent when a tower reaches a new rank (its stats have already been raised)
+pub struct TowerPromoted {
+    pub tower: Entity,
+    pub rank: usize,
+}
+
+pub fn spawn_rank_pip_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(RankPipAssets {
+        mesh: meshes.add(Mesh::from(shape::Box::new(0.3, 0.06, 0.3))),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1., 0.8, 0.),
+            emissive: Color::rgb(0.5, 0.4, 0.),
+            ..Default::default()
+        }),
+    });
+}
+
+/// credits towers with the damage and kills of their projectiles and status effects
+pub fn gain_experience(
+    mut dealt_events: EventReader<health::DamageDealt>,
+    mut died_events: EventReader<health::EntityDied>,
+    mut query: Query<&mut Veterancy>,
+) {
+    for ev in dealt_events.iter() {
+        if let Some(mut veterancy) = ev.source.and_then(|source| query.get_mut(source).ok()) {
+            veterancy.damage += ev.amount;
+        }
+    }
+    for ev in died_events.iter() {
+        if let Some(mut veterancy) = ev.killer.and_then(|killer| query.get_mut(killer).ok()) {
+            veterancy.kills += 1;
+        }
+    }
+}
+
+/// raises a tower's damage, fire rate and range for every new rank and adds a pip above it
+#[allow(clippy::type_complexity)]
+pub fn promote_towers(
+    mut commands: Commands,
+    pip_assets: Res<RankPipAssets>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Veterancy,
+            &mut tower::Range,
+            Option<&mut tower::Shooting>,
+            Option<&mut damage::Damage>,
+        ),
+        Changed<Veterancy>,
+    >,
+    mut promoted_events: EventWriter<TowerPromoted>,
+) {
+    for (entity, mut veterancy, mut range, mut shooting, mut damage) in query.iter_mut() {
+        let rank = rank_for(veterancy.experience());
+        while veterancy.rank < rank {
+            veterancy.rank += 1;
+            range.range += RANK_RANGE;
+            if let Some(shooting) = shooting.as_mut() {
+                let seconds = shooting.timer.duration().as_secs_f32() / RANK_FIRE_RATE;
+                shooting
+                    .timer
+                    .set_duration(std::time::Duration::from_secs_f32(seconds));
+            }
+            if let Some(damage) = damage.as_mut() {
+                damage.amount += (damage.amount / 10).max(1);
+            }
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn((
+                    PbrBundle {
+                        mesh: pip_assets.mesh.clone(),
+                        material: pip_assets.material.clone(),
+                        transform: Transform::from_xyz(0., 2.5 + 0.12 * veterancy.rank as f32, 0.),
+                        ..Default::default()
+                    },
+                    RankPip,
+                    Name::new("RankPip"),
+                ));
+            });
+            promoted_events.send(TowerPromoted {
+                tower: entity,
+                rank: veterancy.rank,
+            });
+        }
+    }
+}
+
+pub fn log_promotions(mut promoted_events: EventReader<TowerPromoted>) {
+    for ev in promoted_events.iter() {
+        info!("{:?} promoted to rank {}", ev.tower, ev.rank);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_follow_experience() {
+        assert_eq!(rank_for(0), 0);
+        assert_eq!(rank_for(99), 0);
+        assert_eq!(rank_for(100), 1);
+        assert_eq!(rank_for(10_000), RANK_EXPERIENCE.len());
+        assert_eq!(next_rank_in(250), Some(50));
+        assert_eq!(next_rank_in(10_000), None);
+    }
+
+    #[test]
+    fn kills_count_for_extra_experience() {
+        let veterancy = Veterancy {
+            kills: 3,
+            damage: 50,
+            rank: 0,
+        };
+        assert_eq!(veterancy.experience(), 50 + 3 * KILL_EXPERIENCE);
+    }
+}
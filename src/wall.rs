@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+use bevy_mod_picking::Selection;
+use std::collections::HashSet;
+
+use crate::assets;
+use crate::damage;
+use crate::economy;
+use crate::enemy;
+use crate::flow_field;
+use crate::goal;
+use crate::grid;
+use crate::health;
+
+const WALL_COST: usize = 10;
+const WALL_HP: usize = 60;
+/// damage per second each cut off enemy does to a wall it's up against
+const WALL_DAMAGE_PER_SECOND: f32 = 4.;
+const ATTACK_RANGE: f32 = 1.3;
+/// walls are scaled down a little so neighbouring posts don't touch
+const WALL_SCALE: f32 = 0.8;
+
+pub struct WallPlugin;
+impl Plugin for WallPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Wall>()
+            .register_type::<WallLink>()
+            .add_system(build_wall)
+            .add_system(connect_walls)
+            .add_system(attack_walls.run_if(in_state(goal::GameState::Playing)))
+            // walls have to be read before they are despawned at the end of the frame
+            .add_system(
+                free_destroyed_walls
+                    .after(health::apply_damage)
+                    .after(health::regen_health),
+            );
+    }
+}
+
+/// blocks a cell until it's destroyed (enemies path around it, or break through it when cut off)
+#[derive(Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct Wall {
+    /// the grid cell the wall stands on
+    pub cell: Entity,
+    pub coordinates: IVec2,
+    /// damage taken that hasn't added up to a whole hp yet
+    pub accumulated: f32,
+}
+
+impl Default for Wall {
+    fn default() -> Self {
+        Self {
+            cell: Entity::PLACEHOLDER,
+            coordinates: IVec2::ZERO,
+            accumulated: 0.,
+        }
+    }
+}
+
+/// half a wall segment reaching from a wall towards a neighbouring wall
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct WallLink;
+
+/// B builds a wall on the selected cell
+#[allow(clippy::too_many_arguments)]
+pub fn build_wall(
+    keys: Res<Input<KeyCode>>,
+    mut commands: Commands,
+    game_assets: Res<assets::GameAssets>,
+    mut wall_material: Local<Option<Handle<StandardMaterial>>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut wallet: ResMut<economy::Wallet>,
+    mut selected: Query<(Entity, &Transform, &Selection, &mut grid::Cell)>,
+    goal_query: Query<(&Transform, &goal::Goal)>,
+    mut spent_events: EventWriter<economy::GoldSpent>,
+) {
+    if !keys.just_pressed(KeyCode::B) {
+        return;
+    }
+    let Some((cell_entity, cell_transform, _, mut cell)) =
+        selected.iter_mut().find(|t| t.2.selected())
+    else {
+        return;
+    };
+    if cell.is_occupied {
+        info!("there is already something on this cell");
+        return;
+    }
+    let on_goal = goal_query.iter().any(|(transform, goal)| {
+        let goal_position = Vec2::new(transform.translation.x, transform.translation.z);
+        (cell.position - goal_position).length() < goal.radius
+    });
+    if on_goal {
+        info!("walls can't be built on the goal");
+        return;
+    }
+    if !wallet.try_spend(WALL_COST) {
+        info!("not enough gold for a wall ({} gold)", WALL_COST);
+        return;
+    }
+    spent_events.send(economy::GoldSpent {
+        gold: WALL_COST,
+        on: economy::Purchase::Wall,
+    });
+    let material = wall_material
+        .get_or_insert_with(|| materials.add(Color::rgb(0.5, 0.45, 0.4).into()))
+        .clone();
+    commands.spawn((
+        PbrBundle {
+            mesh: game_assets.wall_mesh.clone(),
+            material,
+            transform: Transform::from_translation(
+                cell_transform.translation + Vec3::new(0., 0.1, 0.),
+            )
+            .with_scale(Vec3::new(WALL_SCALE, 1., WALL_SCALE)),
+            ..Default::default()
+        },
+        Wall {
+            cell: cell_entity,
+            coordinates: cell.coordinates,
+            accumulated: 0.,
+        },
+        health::HealthPoints {
+            hp: WALL_HP,
+            max_hp: WALL_HP,
+        },
+        Name::new("Wall"),
+    ));
+    cell.is_occupied = true;
+}
+
+/// rebuilds the segments joining neighbouring walls whenever a wall goes up or down
+pub fn connect_walls(
+    mut commands: Commands,
+    added: Query<(), Added<Wall>>,
+    mut removed: RemovedComponents<Wall>,
+    walls: Query<(Entity, &Wall, &Handle<StandardMaterial>)>,
+    game_assets: Res<assets::GameAssets>,
+) {
+    let walls_changed = !added.is_empty() | (removed.iter().count() > 0);
+    if !walls_changed {
+        return;
+    }
+    let occupied: HashSet<IVec2> = walls.iter().map(|(_, wall, _)| wall.coordinates).collect();
+    for (entity, wall, material) in walls.iter() {
+        let mut wall_commands = commands.entity(entity);
+        wall_commands.despawn_descendants();
+        let position = grid::position_of(wall.coordinates);
+        for neighbour in grid::neighbours(wall.coordinates) {
+            if !occupied.contains(&neighbour) {
+                continue;
+            }
+            let direction = grid::position_of(neighbour) - position;
+            let direction = Vec3::new(direction.x, 0., direction.y);
+            // reach the edge of the cell, where the neighbour's half meets it (undoing the wall's scale)
+            let length = direction.length() / 2. / WALL_SCALE;
+            let offset = direction / 4. / WALL_SCALE;
+            wall_commands.with_children(|parent| {
+                parent.spawn((
+                    PbrBundle {
+                        mesh: game_assets.wall_mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(offset)
+                            .looking_to(direction, Vec3::Y)
+                            .with_scale(Vec3::new(0.5, 0.8, length)),
+                        ..Default::default()
+                    },
+                    WallLink,
+                    Name::new("WallLink"),
+                ));
+            });
+        }
+    }
+}
+
+/// enemies that walls have cut off from the goal attack the closest wall in reach
+pub fn attack_walls(
+    time: Res<Time>,
+    field: Res<flow_field::FlowField>,
+    enemies: Query<&Transform, (With<enemy::Enemy>, Without<enemy::Flying>)>,
+    mut walls: Query<(Entity, &Transform, &mut Wall), Without<enemy::Enemy>>,
+    mut hits: EventWriter<health::Hit>,
+) {
+    for enemy_transform in enemies.iter() {
+        let position = Vec2::new(enemy_transform.translation.x, enemy_transform.translation.z);
+        if field.route(position) != flow_field::Route::CutOff {
+            continue;
+        }
+        let closest = walls
+            .iter_mut()
+            .map(|(_, transform, wall)| {
+                let wall_position = Vec2::new(transform.translation.x, transform.translation.z);
+                ((wall_position - position).length(), wall)
+            })
+            .filter(|(distance, _)| *distance < ATTACK_RANGE)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((_, mut wall)) = closest {
+            wall.accumulated += WALL_DAMAGE_PER_SECOND * time.delta_seconds();
+        }
+    }
+    for (entity, _, mut wall) in walls.iter_mut() {
+        if wall.accumulated < 1. {
+            continue;
+        }
+        let amount = wall.accumulated.floor();
+        wall.accumulated -= amount;
+        hits.send(health::Hit {
+            source: None,
+            target: entity,
+            damage: damage::Damage {
+                amount: amount as usize,
+                ..Default::default()
+            },
+            critical: false,
+        });
+    }
+}
+
+pub fn free_destroyed_walls(
+    mut died_events: EventReader<health::EntityDied>,
+    walls: Query<&Wall>,
+    mut cells: Query<&mut grid::Cell>,
+) {
+    for ev in died_events.iter() {
+        let Ok(wall) = walls.get(ev.entity) else {
+            continue;
+        };
+        if let Ok(mut cell) = cells.get_mut(wall.cell) {
+            cell.is_occupied = false;
+        }
+        info!("wall at {:?} destroyed", wall.coordinates);
+    }
+}
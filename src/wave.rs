@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::assets;
+use crate::enemy;
+use crate::goal;
+use crate::map;
+
+pub struct WavePlugin;
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WaveMember>()
+            .add_asset::<WaveDefinitions>()
+            .add_asset_loader(assets::RonAssetLoader::<WaveDefinitions>::new(&["waves.ron"]))
+            .init_resource::<WaveManager>()
+            .add_event::<WaveStarted>()
+            .add_event::<WaveCleared>()
+            .add_system(
+                run_waves
+                    .before(enemy::spawn_requested_enemies)
+                    .run_if(in_state(goal::GameState::Playing)),
+            )
+            .add_system(log_waves);
+    }
+}
+
+/// all the waves for a map, loaded from a `*.waves.ron` file
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "19120f72-f5d9-40ba-a52d-6f5318f5bb11"]
+pub struct WaveDefinitions {
+    pub waves: Vec<WaveDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WaveDefinition {
+    /// seconds to wait before the wave starts (counted from when the previous wave is cleared)
+    pub delay: f32,
+    pub groups: Vec<SpawnGroup>,
+}
+
+/// a run of identical enemies coming out of one spawn point
+#[derive(Clone, Debug, Deserialize)]
+pub struct SpawnGroup {
+    pub enemy: String,
+    pub count: usize,
+    /// seconds between each enemy in the group
+    pub spacing: f32,
+    pub spawn_point: usize,
+    /// seconds after the wave starts before the group begins spawning
+    #[serde(default)]
+    pub offset: f32,
+}
+
+/// an enemy a wave spawned (or that one of its enemies spawned), which has to be gone before
+/// the wave is cleared
+#[derive(Clone, Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct WaveMember {
+    pub wave: usize,
+}
+
+pub struct WaveStarted {
+    pub wave: usize,
+}
+
+pub struct WaveCleared {
+    pub wave: usize,
+}
+
+#[derive(Default)]
+pub enum WaveState {
+    /// waiting on the wave definitions to load
+    #[default]
+    Loading,
+    /// counting down to the start of the current wave
+    Waiting(Timer),
+    /// groups of the current wave are still spawning
+    Spawning,
+    /// everything has spawned, waiting for the enemies to be cleared
+    Clearing,
+    /// no waves left
+    Finished,
+}
+
+struct GroupSpawner {
+    group: SpawnGroup,
+    timer: Timer,
+    remaining: usize,
+}
+
+/// tracks progress through the wave definitions
+#[derive(Default, Resource)]
+pub struct WaveManager {
+    /// index of the current (or next) wave
+    pub current: usize,
+    pub state: WaveState,
+    spawners: Vec<GroupSpawner>,
+}
+
+impl WaveManager {
+    fn start_wave(&mut self, wave: &WaveDefinition) {
+        self.spawners = wave
+            .groups
+            .iter()
+            .map(|group| GroupSpawner {
+                group: group.clone(),
+                timer: Timer::from_seconds(group.offset, TimerMode::Once),
+                remaining: group.count,
+            })
+            .collect();
+        self.state = WaveState::Spawning;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_waves(
+    time: Res<Time>,
+    game_assets: Res<assets::GameAssets>,
+    definitions: Res<Assets<WaveDefinitions>>,
+    mut manager: ResMut<WaveManager>,
+    spawn_points: Query<(&map::SpawnPoint, &Transform)>,
+    members: Query<&WaveMember>,
+    mut spawn_events: EventWriter<enemy::SpawnEnemy>,
+    mut started_events: EventWriter<WaveStarted>,
+    mut cleared_events: EventWriter<WaveCleared>,
+) {
+    let Some(definitions) = definitions.get(&game_assets.waves) else {
+        return;
+    };
+    let manager = manager.as_mut();
+    match &mut manager.state {
+        WaveState::Loading => {
+            manager.state = match definitions.waves.first() {
+                Some(wave) => WaveState::Waiting(Timer::from_seconds(wave.delay, TimerMode::Once)),
+                None => WaveState::Finished,
+            };
+        }
+        WaveState::Waiting(timer) => {
+            timer.tick(time.delta());
+            if timer.finished() {
+                // the definitions may have been reloaded with fewer waves since
+                match definitions.waves.get(manager.current) {
+                    Some(wave) => {
+                        manager.start_wave(wave);
+                        started_events.send(WaveStarted {
+                            wave: manager.current,
+                        });
+                    }
+                    None => manager.state = WaveState::Finished,
+                }
+            }
+        }
+        WaveState::Spawning => {
+            let rng = &mut rand::thread_rng();
+            for spawner in manager.spawners.iter_mut() {
+                if spawner.remaining == 0 {
+                    continue;
+                }
+                spawner.timer.tick(time.delta());
+                if !spawner.timer.finished() {
+                    continue;
+                }
+                let location = spawn_points
+                    .iter()
+                    .find(|(point, _)| point.id == spawner.group.spawn_point)
+                    .map(|(_, transform)| transform.translation);
+                match location {
+                    Some(location) => {
+                        let (jitter_x, jitter_z) =
+                            (rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5));
+                        spawn_events.send(enemy::SpawnEnemy {
+                            archetype: spawner.group.enemy.clone(),
+                            position: location + Vec3::new(jitter_x, 0., jitter_z),
+                            wave: Some(manager.current),
+                        });
+                        spawner.remaining -= 1;
+                    }
+                    None => {
+                        warn!("no spawn point with id {}", spawner.group.spawn_point);
+                        spawner.remaining = 0;
+                    }
+                }
+                spawner.timer = Timer::from_seconds(spawner.group.spacing, TimerMode::Once);
+            }
+            if manager.spawners.iter().all(|s| s.remaining == 0) {
+                manager.spawners.clear();
+                manager.state = WaveState::Clearing;
+            }
+        }
+        WaveState::Clearing => {
+            // enemies spawned by hand don't hold the wave up
+            if !members.iter().any(|member| member.wave == manager.current) {
+                cleared_events.send(WaveCleared {
+                    wave: manager.current,
+                });
+                manager.current += 1;
+                manager.state = match definitions.waves.get(manager.current) {
+                    Some(wave) => {
+                        WaveState::Waiting(Timer::from_seconds(wave.delay, TimerMode::Once))
+                    }
+                    None => WaveState::Finished,
+                };
+            }
+        }
+        WaveState::Finished => {}
+    }
+}
+
+pub fn log_waves(
+    mut started_events: EventReader<WaveStarted>,
+    mut cleared_events: EventReader<WaveCleared>,
+) {
+    for ev in started_events.iter() {
+        info!("wave {} started", ev.wave + 1);
+    }
+    for ev in cleared_events.iter() {
+        info!("wave {} cleared", ev.wave + 1);
+    }
+}